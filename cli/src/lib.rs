@@ -3,12 +3,16 @@ use std::fs;
 use anyhow::{Result, anyhow};
 use clap::Parser;
 
+pub mod matrix;
+
 #[derive(Parser)]
 struct Args {
-    part: u8,
+    part: String,
 
     #[arg(short = 'i', long)]
     input: Option<String>,
+
+    args: Vec<String>,
 }
 
 pub enum Part {
@@ -16,16 +20,64 @@ pub enum Part {
     Part2(String),
 }
 
+/// A [Command] other than the puzzle parts, e.g. an export.
+pub struct Extra {
+    pub name: String,
+    pub args: Vec<String>,
+    pub input: String,
+}
+
+pub enum Command {
+    Part(Part),
+    Extra(Extra),
+}
+
 /// Gets the [Part] to execute.
 pub fn get_part(default_input: &str) -> Result<Part> {
+    match get_command(default_input, &[])? {
+        Command::Part(part) => Ok(part),
+        Command::Extra(extra) => Err(anyhow!("Invalid part number: {}", extra.name)),
+    }
+}
+
+/// Gets the [Command] to execute.
+///
+/// extras lists the names that are accepted in place of a part number.
+pub fn get_command(default_input: &str, extras: &[&str]) -> Result<Command> {
+    get_command_with_inputless(default_input, extras, &[])
+}
+
+/// Gets the [Command] to execute, like [get_command].
+///
+/// inputless lists more names accepted in place of a part number, for extras that take no input,
+/// e.g. generators. No input is read for them, so their [Extra::input] is empty.
+pub fn get_command_with_inputless(
+    default_input: &str,
+    extras: &[&str],
+    inputless: &[&str],
+) -> Result<Command> {
     let args = Args::parse();
 
+    if inputless.contains(&args.part.as_str()) {
+        return Ok(Command::Extra(Extra {
+            name: args.part,
+            args: args.args,
+            input: String::new(),
+        }));
+    }
+
     let path = args.input.unwrap_or(String::from(default_input));
     let input = String::from(trim_newlines(&fs::read_to_string(path)?));
 
-    match args.part {
-        1 => Ok(Part::Part1(input)),
-        2 => Ok(Part::Part2(input)),
+    match args.part.as_str() {
+        "1" if args.args.is_empty() => Ok(Command::Part(Part::Part1(input))),
+        "2" if args.args.is_empty() => Ok(Command::Part(Part::Part2(input))),
+        "1" | "2" => Err(anyhow!("Unexpected arguments: {:?}", args.args)),
+        name if extras.contains(&name) => Ok(Command::Extra(Extra {
+            name: args.part,
+            args: args.args,
+            input,
+        })),
         _ => Err(anyhow!("Invalid part number: {}", args.part)),
     }
 }
@@ -0,0 +1,62 @@
+//! Square matrices of counts, for puzzles that repeat the same linear step many times.
+
+use std::ops::{Add, Mul};
+
+use num_traits::{One, Zero};
+
+pub type Matrix<T> = Vec<Vec<T>>;
+
+/// Raises a square matrix to exponent by repeated squaring, using reduce to keep values in range.
+pub fn matrix_power<T>(
+    mut matrix: Matrix<T>,
+    mut exponent: u64,
+    reduce: &impl Fn(T) -> T,
+) -> Matrix<T>
+where
+    T: Clone + Zero + One + Add<Output = T> + Mul<Output = T>,
+{
+    let size = matrix.len();
+    let mut result = (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|col| {
+                    if row == col {
+                        reduce(T::one())
+                    } else {
+                        T::zero()
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = matrix_multiply(&result, &matrix, reduce);
+        }
+
+        exponent >>= 1;
+        if exponent > 0 {
+            matrix = matrix_multiply(&matrix, &matrix, reduce);
+        }
+    }
+
+    result
+}
+
+fn matrix_multiply<T>(a: &Matrix<T>, b: &Matrix<T>, reduce: &impl Fn(T) -> T) -> Matrix<T>
+where
+    T: Clone + Zero + Add<Output = T> + Mul<Output = T>,
+{
+    a.iter()
+        .map(|row| {
+            (0..b[0].len())
+                .map(|col| {
+                    row.iter().zip(b).fold(T::zero(), |acc, (value, b_row)| {
+                        reduce(acc + value.clone() * b_row[col].clone())
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
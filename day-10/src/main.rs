@@ -1,163 +1,508 @@
+use std::{fs, str::FromStr};
+
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-10.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-10.txt", &["score", "diagnose"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: &str) -> Result<u32> {
-    input
-        .lines()
-        .map(|line| {
-            if let Some(illegal_close_char) = first_illegal_close_char(line)? {
-                score_illegal_close_char(illegal_close_char)
-            } else {
-                Ok(0)
-            }
-        })
-        .sum()
+    syntax_error_score(input, &Language::from_str(NAVIGATION_SYNTAX)?)
 }
 
 fn part_2(input: &str) -> Result<u64> {
-    let mut scores = input
-        .lines()
-        .map(|line| {
-            if let Some(completion_string) = completion_string(line)? {
-                score_completion_string(&completion_string)
-            } else {
-                Ok(0)
-            }
-        })
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .filter(|&score| score != 0)
-        .collect::<Vec<_>>();
+    middle_completion_score(input, &Language::from_str(NAVIGATION_SYNTAX)?)
+}
 
-    assert_eq!(scores.len() % 2, 1);
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (format, path) = match (extra.name.as_str(), extra.args.as_slice()) {
+        ("score", path) if path.len() < 2 => (None, path.first()),
+        ("diagnose", [format, path @ ..]) if path.len() < 2 => {
+            (Some(format.as_str()), path.first())
+        }
+        ("score", _) => return Err(anyhow!("Usage: score [<LANGUAGE>]")),
+        _ => return Err(anyhow!("Usage: diagnose <text|json> [<LANGUAGE>]")),
+    };
+
+    let language = match path {
+        Some(path) => Language::from_str(&fs::read_to_string(path)?)?,
+        None => Language::from_str(NAVIGATION_SYNTAX)?,
+    };
+
+    match format {
+        None => Ok(format!(
+            "syntax error score: {}\nmiddle completion score: {}\n",
+            syntax_error_score(&extra.input, &language)?,
+            middle_completion_score(&extra.input, &language)?
+        )),
+        Some("text") => diagnostics_to_text(&extra.input, &language),
+        Some("json") => diagnostics_to_json(&extra.input, &language),
+        Some(format) => Err(anyhow!("Invalid format: {}", format)),
+    }
+}
 
-    scores.sort();
+/// The bracket language of the navigation subsystem, with the puzzle's scores.
+const NAVIGATION_SYNTAX: &str = "
+pair ( ) 3 1
+pair [ ] 57 2
+pair { } 1197 3
+pair < > 25137 4
+base 5
+";
 
-    Ok(scores[scores.len() / 2])
+/// A pair of delimiters that must match, with the scores for its close char.
+struct Pair {
+    open: u8,
+    close: u8,
+    illegal_score: u32,
+    completion_score: u64,
 }
 
-fn is_open_char(byte: u8) -> bool {
-    matches!(byte, b'(' | b'[' | b'{' | b'<')
+/// A string literal delimiter. Nothing inside a string literal needs to match, and the escape
+/// char, if any, makes the char after it part of the string.
+struct Quote {
+    quote: u8,
+    escape: Option<u8>,
+    completion_score: u64,
 }
 
-fn is_close_char(byte: u8) -> bool {
-    matches!(byte, b')' | b']' | b'}' | b'>')
+/// A bracket language, and how to score lines that break it.
+struct Language {
+    pairs: Vec<Pair>,
+    quotes: Vec<Quote>,
+
+    /// The factor each completion score is multiplied by before adding the next char.
+    completion_base: u64,
 }
 
-fn is_matching(open_char: u8, close_char: u8) -> Result<bool> {
-    match open_char {
-        b'(' => Ok(close_char == b')'),
-        b'[' => Ok(close_char == b']'),
-        b'{' => Ok(close_char == b'}'),
-        b'<' => Ok(close_char == b'>'),
-        _ => Err(anyhow!("Invalid open char: {}", open_char)),
+impl FromStr for Language {
+    type Err = anyhow::Error;
+
+    /// Parses a spec with one of these on each line:
+    /// - `pair <OPEN> <CLOSE> <ILLEGAL_SCORE> <COMPLETION_SCORE>`
+    /// - `string <QUOTE> <COMPLETION_SCORE> [<ESCAPE>]`
+    /// - `base <COMPLETION_BASE>`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let mut language = Language {
+            pairs: Vec::new(),
+            quotes: Vec::new(),
+            completion_base: 5,
+        };
+        let mut delimiters = Vec::new();
+
+        for line in s.trim().lines() {
+            match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["pair", open, close, illegal_score, completion_score] => {
+                    let pair = Pair {
+                        open: parse_delimiter(open)?,
+                        close: parse_delimiter(close)?,
+                        illegal_score: illegal_score.parse()?,
+                        completion_score: completion_score.parse()?,
+                    };
+
+                    delimiters.extend([pair.open, pair.close]);
+                    language.pairs.push(pair);
+                }
+                ["string", quote, completion_score, escape @ ..] if escape.len() < 2 => {
+                    let quote = Quote {
+                        quote: parse_delimiter(quote)?,
+                        escape: escape
+                            .first()
+                            .map(|escape| parse_delimiter(escape))
+                            .transpose()?,
+                        completion_score: completion_score.parse()?,
+                    };
+
+                    if quote.escape == Some(quote.quote) {
+                        return Err(anyhow!("Invalid escape char: {}", line));
+                    }
+
+                    delimiters.push(quote.quote);
+                    language.quotes.push(quote);
+                }
+                ["base", completion_base] => language.completion_base = completion_base.parse()?,
+                _ => return Err(anyhow!("Cannot parse line: {}", line)),
+            }
+        }
+
+        let mut unique_delimiters = delimiters.clone();
+        unique_delimiters.sort();
+        unique_delimiters.dedup();
+        if unique_delimiters.len() != delimiters.len() {
+            return Err(anyhow!("Duplicate delimiters: {:?}", delimiters));
+        }
+
+        Ok(language)
     }
 }
 
-fn matching_close_char(open_char: u8) -> Result<u8> {
-    match open_char {
-        b'(' => Ok(b')'),
-        b'[' => Ok(b']'),
-        b'{' => Ok(b'}'),
-        b'<' => Ok(b'>'),
-        _ => Err(anyhow!("Invalid open char: {}", open_char)),
+/// Parses a delimiter, which must be a single ASCII char.
+fn parse_delimiter(delimiter: &str) -> Result<u8> {
+    match delimiter.as_bytes() {
+        &[byte] if byte.is_ascii() => Ok(byte),
+        _ => Err(anyhow!("Invalid delimiter: {}", delimiter)),
     }
 }
 
-fn first_illegal_close_char(line: &str) -> Result<Option<u8>> {
-    let mut open_char_stack: Vec<u8> = Vec::new();
-
-    for byte in line.bytes() {
-        if is_open_char(byte) {
-            open_char_stack.push(byte);
-            continue;
-        }
+impl Language {
+    fn pair_by_open(&self, open_char: u8) -> Option<&Pair> {
+        self.pairs.iter().find(|pair| pair.open == open_char)
+    }
 
-        if is_close_char(byte) {
-            let Some(open_char) = open_char_stack.pop() else {
-                return Ok(Some(byte));
-            };
+    fn pair_by_close(&self, close_char: u8) -> Option<&Pair> {
+        self.pairs.iter().find(|pair| pair.close == close_char)
+    }
 
-            if !is_matching(open_char, byte)? {
-                return Ok(Some(byte));
-            }
+    fn quote(&self, quote_char: u8) -> Option<&Quote> {
+        self.quotes.iter().find(|quote| quote.quote == quote_char)
+    }
 
-            continue;
+    fn score_illegal_close_char(&self, close_char: u8) -> Result<u32> {
+        match self.pair_by_close(close_char) {
+            Some(pair) => Ok(pair.illegal_score),
+            None => Err(anyhow!("Invalid close char: {}", close_char)),
         }
+    }
 
-        return Err(anyhow!("Invalid char: {}", byte));
+    fn score_completion_string(&self, completion_string: &[u8]) -> Result<u64> {
+        completion_string
+            .iter()
+            .try_fold(0_u64, |acc, &close_char| {
+                acc.checked_mul(self.completion_base)
+                    .and_then(|acc| acc.checked_add(self.score_completion_string_char(close_char)?))
+                    .ok_or(anyhow!(
+                        "Cannot score completion string: {:?}",
+                        completion_string
+                    ))
+            })
     }
 
-    Ok(None)
+    fn score_completion_string_char(&self, close_char: u8) -> Option<u64> {
+        self.pair_by_close(close_char)
+            .map(|pair| pair.completion_score)
+            .or(self.quote(close_char).map(|quote| quote.completion_score))
+    }
+}
+
+/// An open char, or the quote of a string literal, that still needs closing.
+#[derive(Clone, Copy)]
+struct Opener {
+    /// 1-based column of the open char.
+    column: usize,
+    open_char: u8,
+    close_char: u8,
 }
 
-fn score_illegal_close_char(close_char: u8) -> Result<u32> {
-    match close_char {
-        b')' => Ok(3),
-        b']' => Ok(57),
-        b'}' => Ok(1197),
-        b'>' => Ok(25137),
-        _ => Err(anyhow!("Invalid close char: {}", close_char)),
+/// The outcome of checking a line against a [Language].
+enum Check {
+    Complete,
+
+    /// The close char at column closes nothing.
+    UnexpectedClose {
+        column: usize,
+        close_char: u8,
+    },
+
+    /// The close char at column does not match the innermost opener.
+    MismatchedPair {
+        column: usize,
+        close_char: u8,
+        opener: Opener,
+    },
+
+    /// The line ends with openers left, the innermost being opener.
+    Unterminated {
+        completion_string: Vec<u8>,
+        opener: Opener,
+    },
+}
+
+/// A change that makes a line valid, or at least moves its first error further along.
+enum Fix {
+    Delete { column: usize },
+    Replace { column: usize, close_char: u8 },
+    Append(Vec<u8>),
+}
+
+impl Check {
+    fn kind(&self) -> &str {
+        match self {
+            Check::Complete => "complete",
+            Check::UnexpectedClose { .. } => "unexpected close",
+            Check::MismatchedPair { .. } => "mismatched pair",
+            Check::Unterminated { .. } => "unterminated",
+        }
+    }
+
+    fn fix(&self) -> Option<Fix> {
+        match self {
+            Check::Complete => None,
+            &Check::UnexpectedClose { column, .. } => Some(Fix::Delete { column }),
+            &Check::MismatchedPair { column, opener, .. } => Some(Fix::Replace {
+                column,
+                close_char: opener.close_char,
+            }),
+            Check::Unterminated {
+                completion_string, ..
+            } => Some(Fix::Append(completion_string.clone())),
+        }
     }
 }
 
-fn completion_string(line: &str) -> Result<Option<Vec<u8>>> {
-    let mut open_char_stack: Vec<u8> = Vec::new();
+fn check_line(line: &str, language: &Language) -> Result<Check> {
+    let mut open_pairs: Vec<Opener> = Vec::new();
+
+    // The string literal being read, if any, and whether the next char is escaped.
+    let mut string: Option<(&Quote, Opener, bool)> = None;
+
+    for (column, byte) in (1..).zip(line.bytes()) {
+        if let Some((quote, opener, escaped)) = string {
+            string = match escaped {
+                true => Some((quote, opener, false)),
+                false if quote.escape == Some(byte) => Some((quote, opener, true)),
+                false if quote.quote == byte => None,
+                false => string,
+            };
 
-    for byte in line.bytes() {
-        if is_open_char(byte) {
-            open_char_stack.push(byte);
             continue;
         }
 
-        if is_close_char(byte) {
-            let Some(open_char) = open_char_stack.pop() else {
-                return Ok(None);
+        if let Some(quote) = language.quote(byte) {
+            let opener = Opener {
+                column,
+                open_char: byte,
+                close_char: byte,
             };
 
-            if !is_matching(open_char, byte)? {
-                return Ok(None);
-            }
+            string = Some((quote, opener, false));
+            continue;
+        }
 
+        if let Some(pair) = language.pair_by_open(byte) {
+            open_pairs.push(Opener {
+                column,
+                open_char: byte,
+                close_char: pair.close,
+            });
             continue;
         }
 
-        return Err(anyhow!("Invalid char: {}", byte));
+        if language.pair_by_close(byte).is_some() {
+            match open_pairs.pop() {
+                Some(opener) if opener.close_char == byte => continue,
+                Some(opener) => {
+                    return Ok(Check::MismatchedPair {
+                        column,
+                        close_char: byte,
+                        opener,
+                    });
+                }
+                None => {
+                    return Ok(Check::UnexpectedClose {
+                        column,
+                        close_char: byte,
+                    });
+                }
+            }
+        }
+
+        return Err(anyhow!("Invalid char at column {}: {}", column, byte));
     }
 
-    let mut close_char_stack = Vec::new();
+    let mut completion_string = Vec::new();
+    let mut innermost_opener = open_pairs.last().copied();
+
+    if let Some((quote, opener, escaped)) = string {
+        // An escaped quote would not close the string, so complete the escape first.
+        if escaped {
+            completion_string.push(quote.quote);
+        }
 
-    open_char_stack.reverse();
-    for open_char in open_char_stack {
-        close_char_stack.push(matching_close_char(open_char)?);
+        completion_string.push(quote.quote);
+        innermost_opener = Some(opener);
     }
 
-    Ok(Some(close_char_stack))
+    completion_string.extend(open_pairs.iter().rev().map(|opener| opener.close_char));
+
+    match innermost_opener {
+        Some(opener) => Ok(Check::Unterminated {
+            completion_string,
+            opener,
+        }),
+        None => Ok(Check::Complete),
+    }
 }
 
-fn score_completion_string(completion_string: &[u8]) -> Result<u64> {
-    completion_string.iter().try_fold(0, |acc, close_char| {
-        Ok(acc * 5 + score_completion_string_char(*close_char)?)
-    })
+/// Sums the scores of the illegal close chars in corrupted lines.
+fn syntax_error_score(input: &str, language: &Language) -> Result<u32> {
+    input
+        .lines()
+        .map(|line| match check_line(line, language)? {
+            Check::UnexpectedClose { close_char, .. }
+            | Check::MismatchedPair { close_char, .. } => {
+                language.score_illegal_close_char(close_char)
+            }
+            _ => Ok(0),
+        })
+        .sum()
 }
 
-fn score_completion_string_char(close_char: u8) -> Result<u64> {
-    match close_char {
-        b')' => Ok(1),
-        b']' => Ok(2),
-        b'}' => Ok(3),
-        b'>' => Ok(4),
-        _ => Err(anyhow!("Invalid close char: {}", close_char)),
+/// Finds the middle score of the completion strings of incomplete lines.
+fn middle_completion_score(input: &str, language: &Language) -> Result<u64> {
+    let mut scores = Vec::new();
+
+    for line in input.lines() {
+        if let Check::Unterminated {
+            completion_string, ..
+        } = check_line(line, language)?
+        {
+            scores.push(language.score_completion_string(&completion_string)?);
+        }
     }
+
+    if scores.len() % 2 == 0 {
+        return Err(anyhow!(
+            "Cannot find middle of {} completion scores",
+            scores.len()
+        ));
+    }
+
+    scores.sort();
+
+    Ok(scores[scores.len() / 2])
+}
+
+/// Describes each line of input, one per row, with any error and how to fix it.
+fn diagnostics_to_text(input: &str, language: &Language) -> Result<String> {
+    let mut text = String::new();
+
+    for (line_number, line) in (1..).zip(input.lines()) {
+        let check = check_line(line, language)?;
+
+        let error = match &check {
+            Check::Complete => String::from("complete"),
+            Check::UnexpectedClose { column, close_char } => format!(
+                "column {}: unexpected close, found {} with nothing open",
+                column, *close_char as char
+            ),
+            Check::MismatchedPair {
+                column,
+                close_char,
+                opener,
+            } => format!(
+                "column {}: mismatched pair, expected {} to close {} at column {} but found {}",
+                column,
+                opener.close_char as char,
+                opener.open_char as char,
+                opener.column,
+                *close_char as char
+            ),
+            Check::Unterminated { opener, .. } => format!(
+                "column {}: unterminated, {} at column {} is not closed",
+                line.len() + 1,
+                opener.open_char as char,
+                opener.column
+            ),
+        };
+
+        let fix = match check.fix() {
+            None => String::new(),
+            Some(Fix::Delete { column }) => format!("; fix: delete column {column}"),
+            Some(Fix::Replace { column, close_char }) => {
+                format!(
+                    "; fix: replace column {} with {}",
+                    column, close_char as char
+                )
+            }
+            Some(Fix::Append(completion_string)) => format!(
+                "; fix: append {}",
+                String::from_utf8_lossy(&completion_string)
+            ),
+        };
+
+        text += &format!("line {line_number}: {error}{fix}\n");
+    }
+
+    Ok(text)
+}
+
+/// Describes each line of input as a JSON array of objects, with null for what does not apply.
+fn diagnostics_to_json(input: &str, language: &Language) -> Result<String> {
+    let mut objects = Vec::new();
+
+    for (line_number, line) in (1..).zip(input.lines()) {
+        let check = check_line(line, language)?;
+
+        let (column, found, opener) = match &check {
+            Check::Complete => (None, None, None),
+            &Check::UnexpectedClose { column, close_char } => {
+                (Some(column), Some(close_char), None)
+            }
+            &Check::MismatchedPair {
+                column,
+                close_char,
+                opener,
+            } => (Some(column), Some(close_char), Some(opener)),
+            &Check::Unterminated { opener, .. } => (Some(line.len() + 1), None, Some(opener)),
+        };
+
+        let fix = match check.fix() {
+            None => String::from("null"),
+            Some(Fix::Delete { column }) => {
+                format!(r#"{{"action": "delete", "column": {column}}}"#)
+            }
+            Some(Fix::Replace { column, close_char }) => format!(
+                r#"{{"action": "replace", "column": {}, "text": {}}}"#,
+                column,
+                json_string(&[close_char])
+            ),
+            Some(Fix::Append(completion_string)) => format!(
+                r#"{{"action": "append", "text": {}}}"#,
+                json_string(&completion_string)
+            ),
+        };
+
+        objects.push(format!(
+            r#"{{"line": {}, "kind": "{}", "column": {}, "found": {}, "opener": {}, "fix": {}}}"#,
+            line_number,
+            check.kind(),
+            column.map_or(String::from("null"), |column| column.to_string()),
+            found.map_or(String::from("null"), |found| json_string(&[found])),
+            opener.map_or(String::from("null"), |opener| format!(
+                r#"{{"column": {}, "char": {}}}"#,
+                opener.column,
+                json_string(&[opener.open_char])
+            )),
+            fix
+        ));
+    }
+
+    Ok(format!("[\n  {}\n]\n", objects.join(",\n  ")))
+}
+
+/// Quotes ASCII bytes as a JSON string.
+fn json_string(bytes: &[u8]) -> String {
+    let mut string = String::from("\"");
+
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => string += &format!("\\{}", byte as char),
+            byte if byte.is_ascii_control() => string += &format!("\\u{byte:04x}"),
+            byte => string.push(byte as char),
+        }
+    }
+
+    string + "\""
 }
 
 #[cfg(test)]
@@ -192,4 +537,65 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn string_literals() -> Result<()> {
+        let language = Language::from_str(
+            r#"
+pair ( ) 10 1
+pair [ ] 20 2
+string " 3 \
+base 4
+"#,
+        )?;
+
+        assert_eq!(
+            syntax_error_score(r#"("(]")"#, &language)?,
+            0,
+            "brackets in strings do not count"
+        );
+        assert_eq!(syntax_error_score(r#"("\")]"#, &language)?, 0);
+        assert_eq!(syntax_error_score(r#"("\"")]"#, &language)?, 20);
+        assert_eq!(
+            middle_completion_score(r#"["\""#, &language)?,
+            3 * 4 + 2,
+            "the open string is completed first"
+        );
+        assert_eq!(middle_completion_score(r#"[("\"#, &language)?, 246);
+        assert!(check_line("(x)", &language).is_err());
+
+        assert!(Language::from_str("pair ( ( 1 1").is_err());
+        assert!(Language::from_str("pair ( ) 1 1\nstring ) 1").is_err());
+        assert!(Language::from_str("string \" 1 \"").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn diagnostics() -> Result<()> {
+        let language = Language::from_str(NAVIGATION_SYNTAX)?;
+        let input = "{([(<{}[<>[]}>{[]{[(<()>\n())\n[<>\n()";
+
+        assert_eq!(
+            diagnostics_to_text(input, &language)?,
+            "line 1: column 13: mismatched pair, expected ] to close [ at column 8 but found }; \
+             fix: replace column 13 with ]\n\
+             line 2: column 3: unexpected close, found ) with nothing open; fix: delete column 3\n\
+             line 3: column 4: unterminated, [ at column 1 is not closed; fix: append ]\n\
+             line 4: complete\n"
+        );
+        assert_eq!(
+            diagnostics_to_json(
+                "())\n[\"",
+                &Language::from_str("pair ( ) 1 1\npair [ ] 2 2\nstring \" 3")?
+            )?,
+            r#"[
+  {"line": 1, "kind": "unexpected close", "column": 3, "found": ")", "opener": null, "fix": {"action": "delete", "column": 3}},
+  {"line": 2, "kind": "unterminated", "column": 3, "found": null, "opener": {"column": 2, "char": "\""}, "fix": {"action": "append", "text": "\"]"}}
+]
+"#
+        );
+
+        Ok(())
+    }
 }
@@ -1,82 +1,172 @@
+use std::collections::HashMap;
+
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-11.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-11.txt", &["flashes", "sync", "stats"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: &str) -> Result<u32> {
     let mut grid = parse_input_into_grid(input)?;
+    let model = EnergyModel::default();
 
     let mut flashes = 0;
 
     for _ in 0..100 {
-        grid = step(grid, &mut flashes);
+        grid = step(grid, &model, &mut flashes);
     }
 
     Ok(flashes)
 }
 
 fn part_2(input: &str) -> Result<usize> {
-    let mut grid = parse_input_into_grid(input)?;
+    match find_synchronisation(parse_input_into_grid(input)?, &EnergyModel::default()) {
+        Synchronisation::Synchronised { step } => Ok(step),
+        Synchronisation::Cycle { start, period } => Err(anyhow!(
+            "Cannot synchronise: cycle of period {} from step {}",
+            period,
+            start
+        )),
+    }
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (steps, options) = match (extra.name.as_str(), extra.args.split_first()) {
+        ("sync", _) => (0, extra.args.as_slice()),
+        (_, Some((steps, options))) => (steps.parse::<usize>()?, options),
+        _ => {
+            return Err(anyhow!(
+                "Usage: {} <STEPS> [threshold=<ENERGY>] [wraparound]",
+                extra.name
+            ));
+        }
+    };
+    let model = parse_options(options)?;
+    let grid = parse_input_into_grid(&extra.input)?;
+
+    match extra.name.as_str() {
+        "sync" => match find_synchronisation(grid, &model) {
+            Synchronisation::Synchronised { step } => Ok(format!("synchronised at step {step}\n")),
+            Synchronisation::Cycle { start, period } => Ok(format!(
+                "never synchronised, cycle of period {period} from step {start}\n"
+            )),
+        },
+        "stats" => Ok(simulate(grid, &model, steps).1.to_csv()),
+        _ => {
+            let (grid, statistics) = simulate(grid, &model, steps);
+
+            Ok(format!(
+                "{}\n{}",
+                statistics.per_step.iter().sum::<u32>(),
+                grid_to_string(&grid)
+            ))
+        }
+    }
+}
 
-    let mut steps = 1;
+/// Parses the [EnergyModel] options of an extra command.
+fn parse_options(options: &[String]) -> Result<EnergyModel> {
+    let mut model = EnergyModel::default();
 
-    loop {
-        let mut flashes = 0;
-        grid = step(grid, &mut flashes);
-        if flashes == 100 {
-            break;
+    for option in options {
+        match option.split_once("=") {
+            Some(("threshold", threshold)) => model.flash_threshold = threshold.parse()?,
+            None if option == "wraparound" => model.wraparound = true,
+            _ => return Err(anyhow!("Invalid option: {}", option)),
         }
+    }
 
-        steps += 1;
+    // Leave room for all the energy an octopus can gain in a step before it is marked as flashed.
+    if model.flash_threshold > u8::MAX - 10 {
+        return Err(anyhow!(
+            "Invalid flash threshold: {}",
+            model.flash_threshold
+        ));
     }
 
-    Ok(steps)
+    Ok(model)
 }
 
 type Coord = (usize, usize);
 
-type Grid = [[u8; 10]; 10];
+/// Energy levels by row, then column. All rows have the same length.
+type Grid = Vec<Vec<u8>>;
+
+/// How octopuses gain energy and flash.
+struct EnergyModel {
+    /// An octopus flashes when its energy goes above this.
+    flash_threshold: u8,
+
+    /// Whether the edges of the grid wrap around to the opposite edges.
+    wraparound: bool,
+}
+
+impl Default for EnergyModel {
+    fn default() -> Self {
+        EnergyModel {
+            flash_threshold: 9,
+            wraparound: false,
+        }
+    }
+}
 
 fn parse_input_into_grid(input: &str) -> Result<Grid> {
-    let mut grid = [[0; 10]; 10];
+    let grid = input
+        .lines()
+        .map(|line| {
+            line.bytes()
+                .map(|byte| match byte {
+                    b'0'..=b'9' => Ok(byte - b'0'),
+                    _ => Err(anyhow!("Invalid energy level: {}", line)),
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .collect::<Result<Grid>>()?;
 
-    let lines = input.lines().collect::<Vec<_>>();
-    if lines.len() != 10 {
+    if grid.is_empty() || grid[0].is_empty() {
         return Err(anyhow!("Incorrect input size: {}", input));
     }
 
-    for (row, line) in lines.into_iter().enumerate() {
-        if line.len() != 10 {
-            return Err(anyhow!("Incorrect line length: {}", line));
-        }
-
-        for (col, byte) in line.bytes().enumerate() {
-            grid[row][col] = byte - b'0';
-        }
+    if let Some(line) = grid.iter().find(|line| line.len() != grid[0].len()) {
+        return Err(anyhow!("Incorrect line length: {:?}", line));
     }
 
     Ok(grid)
 }
 
-fn step(mut grid: Grid, flashes: &mut u32) -> Grid {
+fn grid_to_string(grid: &Grid) -> String {
+    grid.iter()
+        .map(|row| {
+            row.iter()
+                .map(|energy| energy.to_string())
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+fn step(mut grid: Grid, model: &EnergyModel, flashes: &mut u32) -> Grid {
     // Add 1 throughout.
     add_one(&mut grid);
 
     // Resolve explosions.
-    let mut pending_flashes = check_pending_flashes(&grid);
+    let mut pending_flashes = check_pending_flashes(&grid, model.flash_threshold);
     while !pending_flashes.is_empty() {
         for pending_flash in pending_flashes {
-            flash(pending_flash, &mut grid);
+            flash(pending_flash, &mut grid, model.wraparound);
         }
 
-        pending_flashes = check_pending_flashes(&grid);
+        pending_flashes = check_pending_flashes(&grid, model.flash_threshold);
     }
 
     // Substitute explosions with 0 and update flashes.
@@ -85,18 +175,114 @@ fn step(mut grid: Grid, flashes: &mut u32) -> Grid {
     grid
 }
 
+/// When all the octopuses first flash at once.
+#[derive(Debug, PartialEq)]
+enum Synchronisation {
+    Synchronised {
+        step: usize,
+    },
+
+    /// The grid repeats itself, every period steps from step start on, without ever synchronising.
+    Cycle {
+        start: usize,
+        period: usize,
+    },
+}
+
+/// Steps until all the octopuses flash at once, or until the grid repeats itself.
+///
+/// The steps are deterministic, so a repeated grid means that synchronisation will never happen.
+fn find_synchronisation(mut grid: Grid, model: &EnergyModel) -> Synchronisation {
+    let octopuses = grid.len() * grid[0].len();
+    let mut seen = HashMap::from([(grid.clone(), 0)]);
+
+    for step_number in 1.. {
+        let mut flashes = 0;
+        grid = step(grid, model, &mut flashes);
+        if flashes as usize == octopuses {
+            return Synchronisation::Synchronised { step: step_number };
+        }
+
+        if let Some(start) = seen.insert(grid.clone(), step_number) {
+            return Synchronisation::Cycle {
+                start,
+                period: step_number - start,
+            };
+        }
+    }
+
+    unreachable!()
+}
+
+/// Flash counts over a run of steps.
+struct FlashStatistics {
+    /// The number of flashes in each step.
+    per_step: Vec<u32>,
+
+    /// The number of steps in which each octopus flashed, by row, then column.
+    per_cell: Vec<Vec<u32>>,
+}
+
+impl FlashStatistics {
+    /// Renders the statistics as CSV: step and flashes with a header row, then a blank line and
+    /// the per cell counts as rows of the grid.
+    fn to_csv(&self) -> String {
+        let mut csv = String::from("step,flashes\n");
+        for (step, flashes) in (1..).zip(&self.per_step) {
+            csv += &format!("{step},{flashes}\n");
+        }
+
+        csv += "\n";
+        for row in &self.per_cell {
+            csv += &row
+                .iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            csv += "\n";
+        }
+
+        csv
+    }
+}
+
+/// Runs steps, recording [FlashStatistics] along the way.
+fn simulate(mut grid: Grid, model: &EnergyModel, steps: usize) -> (Grid, FlashStatistics) {
+    let mut statistics = FlashStatistics {
+        per_step: Vec::new(),
+        per_cell: vec![vec![0; grid[0].len()]; grid.len()],
+    };
+
+    for _ in 0..steps {
+        let mut flashes = 0;
+        grid = step(grid, model, &mut flashes);
+        statistics.per_step.push(flashes);
+
+        // Every octopus gains energy in a step, so only those that flashed are at 0.
+        for (counts, row) in statistics.per_cell.iter_mut().zip(&grid) {
+            for (count, &energy) in counts.iter_mut().zip(row) {
+                if energy == 0 {
+                    *count += 1;
+                }
+            }
+        }
+    }
+
+    (grid, statistics)
+}
+
 fn add_one(grid: &mut Grid) {
     grid.iter_mut()
         .for_each(|row| row.iter_mut().for_each(|byte| *byte += 1));
 }
 
-/// Looks for values that are greater than 9 that are not already flashed (u8::MAX).
-fn check_pending_flashes(grid: &Grid) -> Vec<Coord> {
+/// Looks for values that are greater than threshold that are not already flashed (u8::MAX).
+fn check_pending_flashes(grid: &Grid, threshold: u8) -> Vec<Coord> {
     let mut coords = Vec::new();
 
     for (row, bytes) in grid.iter().enumerate() {
         for (col, byte) in bytes.iter().enumerate() {
-            if *byte > 9 && *byte != u8::MAX {
+            if *byte > threshold && *byte != u8::MAX {
                 coords.push((row, col));
             }
         }
@@ -105,33 +291,38 @@ fn check_pending_flashes(grid: &Grid) -> Vec<Coord> {
     coords
 }
 
+/// Gets the up to 8 cells around pos, which are always 8 with wraparound unless the grid is very
+/// small.
+fn neighbours(pos: Coord, grid_size: Coord, wraparound: bool) -> Vec<Coord> {
+    let (rows, cols) = (grid_size.0 as isize, grid_size.1 as isize);
+    let mut neighbours = Vec::new();
+
+    for row_offset in -1..=1 {
+        for col_offset in -1..=1 {
+            let (mut row, mut col) = (pos.0 as isize + row_offset, pos.1 as isize + col_offset);
+
+            if wraparound {
+                (row, col) = (row.rem_euclid(rows), col.rem_euclid(cols));
+            } else if row < 0 || row >= rows || col < 0 || col >= cols {
+                continue;
+            }
+
+            let neighbour = (row as usize, col as usize);
+            if neighbour != pos && !neighbours.contains(&neighbour) {
+                neighbours.push(neighbour);
+            }
+        }
+    }
+
+    neighbours
+}
+
 /// Marks pos as flashed (u8::MAX) and adds 1 to all neighbours.
-fn flash(pos: Coord, grid: &mut Grid) {
+fn flash(pos: Coord, grid: &mut Grid, wraparound: bool) {
     grid[pos.0][pos.1] = u8::MAX;
 
-    if pos.0 > 0 {
-        grid[pos.0 - 1][pos.1] = grid[pos.0 - 1][pos.1].saturating_add(1);
-    }
-    if pos.0 > 0 && pos.1 < 9 {
-        grid[pos.0 - 1][pos.1 + 1] = grid[pos.0 - 1][pos.1 + 1].saturating_add(1);
-    }
-    if pos.1 < 9 {
-        grid[pos.0][pos.1 + 1] = grid[pos.0][pos.1 + 1].saturating_add(1);
-    }
-    if pos.0 < 9 && pos.1 < 9 {
-        grid[pos.0 + 1][pos.1 + 1] = grid[pos.0 + 1][pos.1 + 1].saturating_add(1);
-    }
-    if pos.0 < 9 {
-        grid[pos.0 + 1][pos.1] = grid[pos.0 + 1][pos.1].saturating_add(1);
-    }
-    if pos.0 < 9 && pos.1 > 0 {
-        grid[pos.0 + 1][pos.1 - 1] = grid[pos.0 + 1][pos.1 - 1].saturating_add(1);
-    }
-    if pos.1 > 0 {
-        grid[pos.0][pos.1 - 1] = grid[pos.0][pos.1 - 1].saturating_add(1);
-    }
-    if pos.0 > 0 && pos.1 > 0 {
-        grid[pos.0 - 1][pos.1 - 1] = grid[pos.0 - 1][pos.1 - 1].saturating_add(1);
+    for (row, col) in neighbours(pos, (grid.len(), grid[0].len()), wraparound) {
+        grid[row][col] = grid[row][col].saturating_add(1);
     }
 }
 
@@ -183,4 +374,59 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn energy_models() -> Result<()> {
+        let grid = parse_input_into_grid("11111\n19991\n19191\n19991\n11111")?;
+
+        let mut flashes = 0;
+        let grid = step(grid, &EnergyModel::default(), &mut flashes);
+        assert_eq!(flashes, 9);
+        assert_eq!(grid_to_string(&grid), "34543\n40004\n50005\n40004\n34543\n");
+
+        let mut flashes = 0;
+        let model = EnergyModel {
+            flash_threshold: 9,
+            wraparound: true,
+        };
+        let grid = step(parse_input_into_grid("900\n000")?, &model, &mut flashes);
+        assert_eq!(flashes, 1);
+        assert_eq!(grid_to_string(&grid), "022\n222\n");
+
+        let mut flashes = 0;
+        let model = EnergyModel {
+            flash_threshold: 2,
+            wraparound: false,
+        };
+        step(parse_input_into_grid("2111")?, &model, &mut flashes);
+        assert_eq!(flashes, 4);
+
+        assert!(parse_input_into_grid("12\n3").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn synchronisation() -> Result<()> {
+        let model = EnergyModel::default();
+
+        assert_eq!(
+            find_synchronisation(parse_input_into_grid(trim_newlines(EXAMPLE_INPUT))?, &model),
+            Synchronisation::Synchronised { step: 195 }
+        );
+        assert_eq!(
+            find_synchronisation(parse_input_into_grid("09")?, &model),
+            Synchronisation::Cycle {
+                start: 0,
+                period: 9
+            }
+        );
+        assert!(part_2("0123").is_err());
+
+        let (grid, statistics) = simulate(parse_input_into_grid("09")?, &model, 3);
+        assert_eq!(grid_to_string(&grid), "42\n");
+        assert_eq!(statistics.to_csv(), "step,flashes\n1,1\n2,0\n3,0\n\n0,1\n");
+
+        Ok(())
+    }
 }
@@ -1,14 +1,18 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use anyhow::{Result, anyhow};
 use pathfinding::prelude::count_paths;
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-12.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-12.txt", &["count", "paths", "dot"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -16,33 +20,66 @@ fn main() {
 fn part_1(input: &str) -> Result<usize> {
     let connections = parse_input_into_connections(input)?;
 
-    Ok(count_paths(
-        Node {
-            label: "start",
-            double_visit_used: false,
-            visited_small_caves: vec!["start"],
-        },
-        |node| successors(node, &connections),
-        |node| node.label == "end",
-    ))
+    count_paths_by_rules(&CaveSystem::new(&connections, VisitRules::PART_1)?)
 }
 
 fn part_2(input: &str) -> Result<usize> {
     let connections = parse_input_into_connections(input)?;
 
-    Ok(count_paths(
-        Node {
-            label: "start",
-            double_visit_used: false,
-            visited_small_caves: vec!["start"],
-        },
-        |node| successors_with_double_visit(node, &connections),
-        |node| node.label == "end",
-    ))
+    count_paths_by_rules(&CaveSystem::new(&connections, VisitRules::PART_2)?)
 }
 
-fn parse_input_into_connections(input: &str) -> Result<HashMap<&str, Vec<&str>>> {
-    let mut connections: HashMap<&str, Vec<&str>> = HashMap::new();
+fn run_extra(extra: &Extra) -> Result<String> {
+    let Some((part, options)) = extra.args.split_first() else {
+        return Err(anyhow!(
+            "Usage: {} <1|2> [revisits=<VISITS>] [max-visits=<VISITS>] [limit=<PATHS>] [via=<CAVE>]...",
+            extra.name
+        ));
+    };
+    let mut rules = match part.as_str() {
+        "1" => VisitRules::PART_1,
+        "2" => VisitRules::PART_2,
+        _ => return Err(anyhow!("Invalid part number: {}", part)),
+    };
+
+    let mut limit = None;
+    let mut required_caves = Vec::new();
+    for option in options {
+        match option.split_once("=") {
+            Some(("revisits", visits)) => rules.revisits = visits.parse()?,
+            Some(("max-visits", visits)) => rules.max_visits = visits.parse()?,
+            Some(("limit", paths)) if extra.name != "count" => limit = Some(paths.parse()?),
+            Some(("via", cave)) if extra.name != "count" => required_caves.push(cave),
+            _ => return Err(anyhow!("Invalid option: {}", option)),
+        }
+    }
+
+    let connections = parse_input_into_connections(&extra.input)?;
+    let cave_system = CaveSystem::new(&connections, rules)?;
+
+    if extra.name == "count" {
+        return Ok(format!("{}\n", count_paths_by_rules(&cave_system)?));
+    }
+
+    let paths = paths(&cave_system)
+        .filter(|path| required_caves.iter().all(|cave| path.contains(cave)))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect::<Vec<_>>();
+
+    if extra.name == "dot" {
+        return Ok(connections_to_dot(&connections, &paths));
+    }
+
+    Ok(paths
+        .into_iter()
+        .map(|path| path.join(",") + "\n")
+        .collect())
+}
+
+type Connections<'a> = HashMap<&'a str, Vec<&'a str>>;
+
+fn parse_input_into_connections(input: &str) -> Result<Connections<'_>> {
+    let mut connections: Connections = HashMap::new();
 
     input.lines().try_for_each(|line| {
         let Some((a, b)) = line.split_once("-") else {
@@ -58,92 +95,296 @@ fn parse_input_into_connections(input: &str) -> Result<HashMap<&str, Vec<&str>>>
     Ok(connections)
 }
 
-#[derive(Eq, Hash, PartialEq)]
-struct Node<'a> {
-    label: &'a str,
-    double_visit_used: bool,
-    visited_small_caves: Vec<&'a str>,
+fn is_big_cave(label: &str) -> bool {
+    label.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// How often small caves may be visited. Big caves may always be visited again.
+#[derive(Clone, Copy)]
+struct VisitRules {
+    /// How many visits to already visited small caves a path may make in total.
+    revisits: usize,
+
+    /// How many times a path may visit any one small cave.
+    max_visits: usize,
 }
 
-fn successors<'a>(node: &Node<'a>, connections: &HashMap<&'a str, Vec<&'a str>>) -> Vec<Node<'a>> {
-    let Node {
-        label,
-        visited_small_caves,
-        ..
-    } = node;
+impl VisitRules {
+    const PART_1: Self = VisitRules {
+        revisits: 0,
+        max_visits: 1,
+    };
 
-    let mut nodes = Vec::new();
+    const PART_2: Self = VisitRules {
+        revisits: 1,
+        max_visits: 2,
+    };
+}
 
-    for connection in &connections[label] {
-        if connection.starts_with(|c: char| c.is_ascii_uppercase()) {
-            // Big cave.
+/// The caves by index, with their [VisitRules].
+struct CaveSystem<'a> {
+    labels: Vec<&'a str>,
+    connections: Vec<Vec<usize>>,
 
-            nodes.push(Node {
-                label: connection,
-                double_visit_used: false,
-                visited_small_caves: visited_small_caves.clone(),
-            });
-        } else if !visited_small_caves.contains(connection) {
-            // Unvisited small cave.
+    /// The bit offset of each small cave's visit count in [Node::visits], or None for big caves.
+    offsets: Vec<Option<usize>>,
 
-            let mut visited_small_caves = visited_small_caves.clone();
-            visited_small_caves.push(connection);
+    /// The number of bits for each visit count, which divides 64 so that no count spans two words.
+    bits: usize,
 
-            nodes.push(Node {
-                label: connection,
-                double_visit_used: false,
-                visited_small_caves,
-            });
+    start: usize,
+    end: usize,
+    rules: VisitRules,
+}
+
+impl<'a> CaveSystem<'a> {
+    fn new(connections: &Connections<'a>, rules: VisitRules) -> Result<Self> {
+        if rules.max_visits == 0 {
+            return Err(anyhow!("Invalid max visits: 0"));
+        }
+
+        let mut labels = connections.keys().copied().collect::<Vec<_>>();
+        labels.sort();
+
+        let index = |label: &str| labels.binary_search(&label).ok();
+        let (Some(start), Some(end)) = (index("start"), index("end")) else {
+            return Err(anyhow!("Missing cave: start or end"));
+        };
+
+        // Two connected big caves would let paths go back and forth forever.
+        for (&label, caves) in connections {
+            if let Some(cave) = caves
+                .iter()
+                .find(|&&cave| is_big_cave(label) && is_big_cave(cave))
+            {
+                return Err(anyhow!("Connected big caves: {}-{}", label, cave));
+            }
+        }
+
+        // Enough bits for every count up to max visits.
+        let bits = ((usize::BITS - rules.max_visits.leading_zeros()) as usize).next_power_of_two();
+        let mut next_offset = 0;
+        let mut offsets = Vec::new();
+        for label in &labels {
+            if is_big_cave(label) {
+                offsets.push(None);
+            } else {
+                offsets.push(Some(next_offset));
+                next_offset += bits;
+            }
+        }
+        let connections = labels
+            .iter()
+            .map(|label| {
+                connections[label]
+                    .iter()
+                    .filter_map(|&cave| index(cave))
+                    .collect()
+            })
+            .collect();
+
+        Ok(CaveSystem {
+            labels,
+            connections,
+            offsets,
+            bits,
+            start,
+            end,
+            rules,
+        })
+    }
+
+    /// The [Node] at start, with start visited.
+    fn start_node(&self) -> Node {
+        let small_caves = self.offsets.iter().flatten().count();
+        let node = Node {
+            cave: self.start,
+            visits: vec![0; (small_caves * self.bits).div_ceil(u64::BITS as usize)],
+            revisits_left: self.rules.revisits,
+        };
+
+        match self.offsets[self.start] {
+            Some(offset) => self.visit(&node, self.start, offset, node.revisits_left),
+            None => node,
         }
     }
 
-    nodes
-}
-
-fn successors_with_double_visit<'a>(
-    node: &Node<'a>,
-    connections: &HashMap<&'a str, Vec<&'a str>>,
-) -> Vec<Node<'a>> {
-    let Node {
-        label,
-        double_visit_used,
-        visited_small_caves,
-    } = node;
-
-    let mut nodes = Vec::new();
-
-    for connection in &connections[label] {
-        if connection.starts_with(|c: char| c.is_ascii_uppercase()) {
-            // Big cave.
-
-            nodes.push(Node {
-                label: connection,
-                double_visit_used: *double_visit_used,
-                visited_small_caves: visited_small_caves.clone(),
-            });
-        } else if !visited_small_caves.contains(connection) {
-            // Unvisited small cave.
-
-            let mut visited_small_caves = visited_small_caves.clone();
-            visited_small_caves.push(connection);
-
-            nodes.push(Node {
-                label: connection,
-                double_visit_used: *double_visit_used,
-                visited_small_caves,
-            });
-        } else if !*double_visit_used && *connection != "start" {
-            // Visited small cave, but double-visit available.
-
-            nodes.push(Node {
-                label: connection,
-                double_visit_used: true,
-                visited_small_caves: visited_small_caves.clone(),
-            });
+    /// Gets the visit count packed at offset.
+    fn visit_count(&self, node: &Node, offset: usize) -> usize {
+        let word = node.visits[offset / u64::BITS as usize];
+        let mask = u64::MAX >> (u64::BITS as usize - self.bits);
+
+        ((word >> (offset % u64::BITS as usize)) & mask) as usize
+    }
+
+    /// Gets the [Node] for moving on to the small cave with the count at offset.
+    fn visit(&self, node: &Node, cave: usize, offset: usize, revisits_left: usize) -> Node {
+        let mut visits = node.visits.clone();
+        visits[offset / u64::BITS as usize] += 1 << (offset % u64::BITS as usize);
+
+        Node {
+            cave,
+            visits,
+            revisits_left,
         }
     }
 
-    nodes
+    /// Gets the [Node]s that can follow node without breaking the [VisitRules]. Start is never
+    /// visited again.
+    fn successors(&self, node: &Node) -> Vec<Node> {
+        let mut nodes = Vec::new();
+
+        for &cave in &self.connections[node.cave] {
+            if cave == self.start {
+                continue;
+            }
+
+            let Some(offset) = self.offsets[cave] else {
+                // Big cave.
+
+                nodes.push(Node {
+                    cave,
+                    ..node.clone()
+                });
+                continue;
+            };
+
+            let visits = self.visit_count(node, offset);
+
+            if visits == 0 {
+                // Unvisited small cave.
+
+                nodes.push(self.visit(node, cave, offset, node.revisits_left));
+            } else if visits < self.rules.max_visits && node.revisits_left > 0 {
+                // Visited small cave, but revisits available.
+
+                nodes.push(self.visit(node, cave, offset, node.revisits_left - 1));
+            }
+        }
+
+        nodes
+    }
+}
+
+/// A cave on a path, with how often the path visited each small cave so far and how many revisits
+/// it has left. Paths that reach the same node continue in the same ways, so counts can be shared.
+#[derive(Clone, Eq, Hash, PartialEq)]
+struct Node {
+    cave: usize,
+
+    /// The visit count of each small cave, packed into words at its [CaveSystem::offsets].
+    visits: Vec<u64>,
+
+    revisits_left: usize,
+}
+
+/// Counts the paths from start to end, memoizing the count from each [Node].
+fn count_paths_by_rules(cave_system: &CaveSystem) -> Result<usize> {
+    Ok(count_paths(
+        cave_system.start_node(),
+        |node| cave_system.successors(node),
+        |node| node.cave == cave_system.end,
+    ))
+}
+
+/// Enumerates the paths from start to end one at a time, depth first, in the order of the
+/// connections.
+struct Paths<'a, 'c> {
+    cave_system: &'c CaveSystem<'a>,
+
+    /// The nodes of the path so far, each with the successors that are still to be explored, last
+    /// first.
+    stack: Vec<(Node, Vec<Node>)>,
+}
+
+impl<'a> Iterator for Paths<'a, '_> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (_, pending) = self.stack.last_mut()?;
+
+            let Some(node) = pending.pop() else {
+                self.stack.pop();
+                continue;
+            };
+
+            if node.cave == self.cave_system.end {
+                let mut path = self
+                    .stack
+                    .iter()
+                    .map(|(node, _)| self.cave_system.labels[node.cave])
+                    .collect::<Vec<_>>();
+                path.push(self.cave_system.labels[node.cave]);
+
+                return Some(path);
+            }
+
+            let mut successors = self.cave_system.successors(&node);
+            successors.reverse();
+
+            self.stack.push((node, successors));
+        }
+    }
+}
+
+/// Gets the [Paths] through cave_system.
+fn paths<'a, 'c>(cave_system: &'c CaveSystem<'a>) -> Paths<'a, 'c> {
+    let start = cave_system.start_node();
+    let mut pending = cave_system.successors(&start);
+    pending.reverse();
+
+    Paths {
+        cave_system,
+        stack: vec![(start, pending)],
+    }
+}
+
+/// Renders the caves as an undirected Graphviz DOT graph, with big caves as boxes. The caves and
+/// connections on paths are highlighted, each connection labelled with the number of paths using
+/// it.
+fn connections_to_dot(connections: &Connections, paths: &[Vec<&str>]) -> String {
+    let mut path_caves = BTreeSet::new();
+    let mut path_connections: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+
+    for path in paths {
+        path_caves.extend(path.iter().copied());
+
+        for pair in path.windows(2) {
+            *path_connections
+                .entry((pair[0].min(pair[1]), pair[0].max(pair[1])))
+                .or_default() += 1;
+        }
+    }
+
+    let mut dot = String::from("graph caves {\n");
+
+    for cave in connections.keys().collect::<BTreeSet<_>>() {
+        let shape = if is_big_cave(cave) { "box" } else { "ellipse" };
+        let highlight = if path_caves.contains(cave) {
+            ", color=red"
+        } else {
+            ""
+        };
+
+        dot += &format!("  \"{cave}\" [shape={shape}{highlight}];\n");
+    }
+
+    let all_connections = connections
+        .iter()
+        .flat_map(|(&a, caves)| caves.iter().map(move |&b| (a.min(b), a.max(b))))
+        .collect::<BTreeSet<_>>();
+
+    for (a, b) in all_connections {
+        let highlight = match path_connections.get(&(a, b)) {
+            Some(count) => format!(" [color=red, penwidth=2, label={count}]"),
+            None => String::new(),
+        };
+
+        dot += &format!("  \"{a}\" -- \"{b}\"{highlight};\n");
+    }
+
+    dot + "}\n"
 }
 
 #[cfg(test)]
@@ -237,4 +478,123 @@ start-RW
 
         Ok(())
     }
+
+    #[test]
+    fn path_enumeration() -> Result<()> {
+        let connections = parse_input_into_connections(trim_newlines(EXAMPLE_INPUT_A))?;
+        let part_1_caves = CaveSystem::new(&connections, VisitRules::PART_1)?;
+
+        assert_eq!(paths(&part_1_caves).count(), 10);
+        assert_eq!(
+            paths(&CaveSystem::new(&connections, VisitRules::PART_2)?).count(),
+            36
+        );
+        assert_eq!(
+            paths(&part_1_caves)
+                .filter(|path| path.contains(&"c"))
+                .skip(3)
+                .take(5)
+                .collect::<Vec<_>>(),
+            vec![
+                vec!["start", "A", "b", "A", "c", "A", "end"],
+                vec!["start", "b", "A", "c", "A", "end"],
+            ]
+        );
+
+        let connections = parse_input_into_connections("start-A\nA-end\nstart-b")?;
+        let paths = paths(&CaveSystem::new(&connections, VisitRules::PART_1)?).collect::<Vec<_>>();
+        assert_eq!(
+            connections_to_dot(&connections, &paths),
+            "graph caves {\n  \
+             \"A\" [shape=box, color=red];\n  \
+             \"b\" [shape=ellipse];\n  \
+             \"end\" [shape=ellipse, color=red];\n  \
+             \"start\" [shape=ellipse, color=red];\n  \
+             \"A\" -- \"end\" [color=red, penwidth=2, label=1];\n  \
+             \"A\" -- \"start\" [color=red, penwidth=2, label=1];\n  \
+             \"b\" -- \"start\";\n\
+             }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn visit_rules() -> Result<()> {
+        let connections = parse_input_into_connections(trim_newlines(EXAMPLE_INPUT_C))?;
+
+        for (revisits, max_visits) in [(0, 1), (1, 2), (2, 2), (2, 3), (3, 2)] {
+            let cave_system = CaveSystem::new(
+                &connections,
+                VisitRules {
+                    revisits,
+                    max_visits,
+                },
+            )?;
+
+            assert_eq!(
+                count_paths_by_rules(&cave_system)?,
+                paths(&cave_system).count()
+            );
+        }
+
+        let connections = parse_input_into_connections("start-a\na-end\na-B\nB-c")?;
+        let cave_system = CaveSystem::new(
+            &connections,
+            VisitRules {
+                revisits: 2,
+                max_visits: 3,
+            },
+        )?;
+        assert_eq!(
+            paths(&cave_system)
+                .map(|path| path.join(","))
+                .collect::<Vec<_>>(),
+            vec![
+                "start,a,end",
+                "start,a,B,a,end",
+                "start,a,B,a,B,a,end",
+                "start,a,B,a,B,c,B,a,end",
+                "start,a,B,c,B,a,end",
+                "start,a,B,c,B,a,B,a,end",
+                "start,a,B,c,B,c,B,a,end",
+            ]
+        );
+
+        assert!(
+            CaveSystem::new(
+                &parse_input_into_connections("start-A\nA-B\nB-end")?,
+                VisitRules::PART_1
+            )
+            .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn many_small_caves() -> Result<()> {
+        let chain = (0..70)
+            .map(|cave| format!("c{cave}"))
+            .chain([String::from("end")])
+            .fold(
+                (String::from("start"), String::new()),
+                |(previous, input), cave| (cave.clone(), input + &format!("{previous}-{cave}\n")),
+            )
+            .1;
+
+        assert_eq!(part_1(trim_newlines(&chain))?, 1);
+        assert_eq!(part_2(trim_newlines(&chain))?, 1);
+
+        // A side cave for every cave in the chain.
+        let sides = (0..70)
+            .map(|cave| format!("c{cave}-s{cave}\n"))
+            .collect::<String>();
+        let input = chain + &sides;
+
+        assert_eq!(part_1(trim_newlines(&input))?, 1);
+        assert_eq!(part_2(trim_newlines(&input))?, 71);
+
+        Ok(())
+    }
 }
@@ -1,38 +1,75 @@
 use std::{
-    cmp::{max, min},
-    collections::HashSet,
+    cmp::{Ordering, max, min},
+    collections::{HashMap, HashSet},
 };
 
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-13.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-13.txt", &["print", "pbm", "unfold"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: &str) -> Result<usize> {
-    let (mut dots, folds) = parse_input_into_dots_and_folds(input)?;
+    let (mut paper, folds) = parse_input_into_paper_and_folds(input)?;
 
     assert!(!folds.is_empty());
 
-    fold_paper(&folds[0], &mut dots);
+    fold_paper(&folds[0], &mut paper)?;
 
-    Ok(dots.len())
+    Ok(paper.dots.len())
 }
 
 fn part_2(input: &str) -> Result<String> {
-    let (mut dots, folds) = parse_input_into_dots_and_folds(input)?;
+    let (mut paper, folds) = parse_input_into_paper_and_folds(input)?;
 
     for fold in &folds {
-        fold_paper(fold, &mut dots);
+        fold_paper(fold, &mut paper)?;
     }
 
-    Ok(print_paper(&dots))
+    read_letters(&paper.dots)
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (mut paper, folds) = parse_input_into_paper_and_folds(&extra.input)?;
+
+    match (extra.name.as_str(), extra.args.as_slice()) {
+        ("print", []) => {
+            fold_all(&folds, &mut paper)?;
+
+            Ok(print_paper(&paper.dots))
+        }
+        ("pbm", []) => {
+            fold_all(&folds, &mut paper)?;
+
+            Ok(paper_to_pbm(&paper))
+        }
+        // The dots are already folded.
+        ("unfold", [limit, format @ ..]) if format.len() < 2 => {
+            let candidates = unfold_candidates(&paper, &folds, limit.parse()?);
+
+            match format.first().map(String::as_str) {
+                None => Ok(candidates
+                    .iter()
+                    .map(paper_to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")),
+                Some("pbm") => Ok(candidates.iter().map(paper_to_pbm).collect()),
+                Some(format) => Err(anyhow!("Invalid format: {}", format)),
+            }
+        }
+        ("unfold", _) => Err(anyhow!("Usage: unfold <LIMIT> [pbm]")),
+        _ => Err(anyhow!("Usage: {}", extra.name)),
+    }
 }
 
 type Coord = (usize, usize);
@@ -45,7 +82,15 @@ enum Axis {
 
 type Fold = (Axis, usize);
 
-fn parse_input_into_dots_and_folds(input: &str) -> Result<(HashSet<Coord>, Vec<Fold>)> {
+/// The dots on a sheet of paper of size (width, height), which grows if a fold needs more room.
+#[derive(Clone)]
+struct Paper {
+    dots: HashSet<Coord>,
+    size: Coord,
+}
+
+/// Parses the dots and the folds. The paper is just large enough for the dots.
+fn parse_input_into_paper_and_folds(input: &str) -> Result<(Paper, Vec<Fold>)> {
     let Some((dots_part, folds_part)) = input.split_once("\n\n") else {
         return Err(anyhow!("Cannot split input into dots and folds: {}", input));
     };
@@ -60,7 +105,11 @@ fn parse_input_into_dots_and_folds(input: &str) -> Result<(HashSet<Coord>, Vec<F
         .map(parse_fold)
         .collect::<Result<Vec<_>>>()?;
 
-    Ok((dots, folds))
+    let size = dots.iter().fold((0, 0), |acc, dot| {
+        (max(acc.0, dot.0 + 1), max(acc.1, dot.1 + 1))
+    });
+
+    Ok((Paper { dots, size }, folds))
 }
 
 fn parse_dot(input: &str) -> Result<Coord> {
@@ -85,17 +134,190 @@ fn parse_fold(input: &str) -> Result<Fold> {
     Ok((axis, index.parse()?))
 }
 
-fn fold_paper(fold: &Fold, dots: &mut HashSet<Coord>) {
+/// Finds where a position along an axis of length size ends up when folding at index, or None for
+/// index itself.
+///
+/// If the part past index is longer, the other part ends up shifted along by the difference.
+fn fold_position(position: usize, index: usize, size: usize) -> Option<usize> {
+    let shift = (size - 1).saturating_sub(2 * index);
+
+    match position.cmp(&index) {
+        Ordering::Less => Some(position + shift),
+        Ordering::Equal => None,
+        Ordering::Greater => Some(2 * index + shift - position),
+    }
+}
+
+/// Gets the length of an axis of length size after folding at index.
+fn folded_size(index: usize, size: usize) -> usize {
+    max(index, (size - 1).saturating_sub(index))
+}
+
+fn fold_paper(fold: &Fold, paper: &mut Paper) -> Result<()> {
     let (axis, index) = fold;
 
-    for dot in dots.clone() {
-        if *axis == Axis::X && dot.0 > *index {
-            dots.remove(&dot);
-            dots.insert((dot.0 - ((dot.0 - *index) * 2), dot.1));
-        } else if *axis == Axis::Y && dot.1 > *index {
-            dots.remove(&dot);
-            dots.insert((dot.0, dot.1 - ((dot.1 - *index) * 2)));
+    // A fold past the edge just reaches beyond the dots.
+    let (width, height) = match axis {
+        Axis::X => (max(paper.size.0, index + 1), paper.size.1),
+        Axis::Y => (paper.size.0, max(paper.size.1, index + 1)),
+    };
+
+    let mut dots = HashSet::new();
+
+    for dot in &paper.dots {
+        let dot = match axis {
+            Axis::X => fold_position(dot.0, *index, width).map(|x| (x, dot.1)),
+            Axis::Y => fold_position(dot.1, *index, height).map(|y| (dot.0, y)),
+        };
+        let Some(dot) = dot else {
+            return Err(anyhow!("Cannot fold through dot at {}", index));
+        };
+
+        dots.insert(dot);
+    }
+
+    paper.dots = dots;
+    paper.size = match axis {
+        Axis::X => (folded_size(*index, width), height),
+        Axis::Y => (width, folded_size(*index, height)),
+    };
+
+    Ok(())
+}
+
+fn fold_all(folds: &[Fold], paper: &mut Paper) -> Result<()> {
+    for fold in folds {
+        fold_paper(fold, paper)?;
+    }
+
+    Ok(())
+}
+
+/// Finds all the papers that, folded in turn, give paper.
+///
+/// Every dot may come from either side of a fold, or both, so there are up to 3 ^ dots candidates
+/// for each fold. Stops after limit candidates.
+fn unfold_candidates(paper: &Paper, folds: &[Fold], limit: usize) -> Vec<Paper> {
+    let mut candidates = Vec::new();
+
+    unfold_into(
+        paper,
+        folds,
+        &unfold_sizes(paper.size, folds),
+        limit,
+        &mut candidates,
+    );
+
+    candidates
+}
+
+/// Works out the size of the paper before each of folds, for a paper that ends up just large
+/// enough for dots of extent size, i.e. no blank margin was folded away.
+///
+/// Each fold leaves a length of at least its index, so earlier folds may force a longer length
+/// than the dots need. Going back from there, a length over index can only come from an
+/// off-centre fold of a paper index + 1 longer, and a length of index from any paper up to twice
+/// as long, of which the longest is taken (shorter ones give a subset of the dots).
+fn unfold_sizes(size: Coord, folds: &[Fold]) -> Vec<Coord> {
+    // The shortest lengths that the folds can leave.
+    let shortest = folds.iter().fold((0, 0), |size, (axis, index)| match axis {
+        Axis::X => (folded_size(*index, max(size.0, index + 1)), size.1),
+        Axis::Y => (size.0, folded_size(*index, max(size.1, index + 1))),
+    });
+
+    let mut size = (max(size.0, shortest.0), max(size.1, shortest.1));
+    let mut sizes = folds
+        .iter()
+        .rev()
+        .map(|(axis, index)| {
+            let length = match axis {
+                Axis::X => &mut size.0,
+                Axis::Y => &mut size.1,
+            };
+            *length += index + 1;
+
+            size
+        })
+        .collect::<Vec<_>>();
+    sizes.reverse();
+
+    sizes
+}
+
+/// Unfolds paper along folds in reverse, where sizes are those of the paper before each fold.
+fn unfold_into(
+    paper: &Paper,
+    folds: &[Fold],
+    sizes: &[Coord],
+    limit: usize,
+    candidates: &mut Vec<Paper>,
+) {
+    let (Some(((axis, index), folds)), Some((&size, sizes))) =
+        (folds.split_last(), sizes.split_last())
+    else {
+        if candidates.len() < limit {
+            candidates.push(paper.clone());
         }
+
+        return;
+    };
+
+    // The ways each dot may be unfolded.
+    let mut dots = paper.dots.iter().copied().collect::<Vec<_>>();
+    dots.sort();
+    let options = dots
+        .into_iter()
+        .map(|dot| {
+            let (position, size) = match axis {
+                Axis::X => (dot.0, size.0),
+                Axis::Y => (dot.1, size.1),
+            };
+            let origins = (0..size)
+                .filter(|&origin| fold_position(origin, *index, size) == Some(position))
+                .map(|origin| match axis {
+                    Axis::X => (origin, dot.1),
+                    Axis::Y => (dot.0, origin),
+                })
+                .collect::<Vec<_>>();
+
+            match origins.as_slice() {
+                &[first, second] => vec![vec![first], vec![second], vec![first, second]],
+                _ => vec![origins],
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if options.iter().any(|option| option[0].is_empty()) {
+        return;
+    }
+
+    // Count through every combination of options.
+    let mut choices = vec![0; options.len()];
+    loop {
+        let unfolded = Paper {
+            dots: options
+                .iter()
+                .zip(&choices)
+                .flat_map(|(option, &choice)| option[choice].iter().copied())
+                .collect(),
+            size,
+        };
+        unfold_into(&unfolded, folds, sizes, limit, candidates);
+
+        if candidates.len() >= limit {
+            return;
+        }
+
+        let Some(digit) = choices
+            .iter()
+            .zip(&options)
+            .position(|(&choice, option)| choice + 1 < option.len())
+        else {
+            return;
+        };
+
+        choices[digit] += 1;
+        choices[..digit].fill(0);
     }
 }
 
@@ -126,7 +348,154 @@ fn print_paper(dots: &HashSet<Coord>) -> String {
         printout.push('\n');
     }
 
-    println!("{}", printout);
+    printout
+}
+
+/// Renders the whole paper, with a # for each dot and a . otherwise.
+fn paper_to_string(paper: &Paper) -> String {
+    let mut printout = String::new();
+
+    for y in 0..paper.size.1 {
+        for x in 0..paper.size.0 {
+            printout.push(if paper.dots.contains(&(x, y)) {
+                '#'
+            } else {
+                '.'
+            });
+        }
+        printout.push('\n');
+    }
+
+    printout
+}
+
+/// Renders the whole paper as a plain (ASCII) PBM image, with the dots in black.
+fn paper_to_pbm(paper: &Paper) -> String {
+    let mut pbm = format!("P1\n{} {}\n", paper.size.0, paper.size.1);
+
+    for y in 0..paper.size.1 {
+        let row = (0..paper.size.0)
+            .map(|x| {
+                if paper.dots.contains(&(x, y)) {
+                    "1"
+                } else {
+                    "0"
+                }
+            })
+            .collect::<Vec<_>>();
+
+        pbm += &row.join(" ");
+        pbm += "\n";
+    }
+
+    pbm
+}
+
+/// The letters of [FONT], in order.
+const FONT_LETTERS: &str = "ABCEFGHIJKLOPRSUZ";
+
+/// The 4 x 6 letters that the folded paper shows, each followed by a blank column.
+const FONT: &str = "
+.##..###...##..####.####..##..#..#..###...##.#..#.#.....##..###..###...###.#..#.####
+#..#.#..#.#..#.#....#....#..#.#..#...#.....#.#.#..#....#..#.#..#.#..#.#....#..#....#
+#..#.###..#....###..###..#....####...#.....#.##...#....#..#.#..#.#..#.#....#..#...#.
+####.#..#.#....#....#....#.##.#..#...#.....#.#.#..#....#..#.###..###...##..#..#..#..
+#..#.#..#.#..#.#....#....#..#.#..#...#..#..#.#.#..#....#..#.#....#.#.....#.#..#.#...
+#..#.###...##..####.#.....###.#..#..###..##..#..#.####..##..#....#..#.###...##..####
+";
+
+const GLYPH_WIDTH: usize = 4;
+
+const GLYPH_HEIGHT: usize = 6;
+
+/// Gets the dots in the glyph at index, as bits in reading order.
+fn glyph(dots: &HashSet<Coord>, index: usize) -> u32 {
+    let mut glyph = 0;
+
+    for y in 0..GLYPH_HEIGHT {
+        for x in 0..GLYPH_WIDTH {
+            glyph <<= 1;
+
+            if dots.contains(&(index * (GLYPH_WIDTH + 1) + x, y)) {
+                glyph |= 1;
+            }
+        }
+    }
+
+    glyph
+}
+
+/// Reads the letters shown by dots, left to right from the origin.
+///
+/// Fails on any dot outside the glyphs, and on unknown glyphs, showing each of them.
+fn read_letters(dots: &HashSet<Coord>) -> Result<String> {
+    let font_dots = FONT
+        .trim()
+        .lines()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.bytes()
+                .enumerate()
+                .filter(|&(_, byte)| byte == b'#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+    let font = FONT_LETTERS
+        .chars()
+        .enumerate()
+        .map(|(index, letter)| (glyph(&font_dots, index), letter))
+        .collect::<HashMap<_, _>>();
+
+    if let Some(dot) = dots
+        .iter()
+        .filter(|dot| dot.1 >= GLYPH_HEIGHT || dot.0 % (GLYPH_WIDTH + 1) == GLYPH_WIDTH)
+        .min()
+    {
+        return Err(anyhow!("Cannot read dot outside letters: {:?}", dot));
+    }
+
+    let letter_count = dots
+        .iter()
+        .map(|dot| dot.0 / (GLYPH_WIDTH + 1) + 1)
+        .max()
+        .unwrap_or_default();
+
+    let mut letters = String::new();
+    let mut unknown_glyphs = Vec::new();
+
+    for index in 0..letter_count {
+        let glyph = glyph(dots, index);
+
+        match font.get(&glyph) {
+            Some(&letter) => letters.push(letter),
+            None => {
+                letters.push('?');
+                unknown_glyphs.push(format!("letter {}:\n{}", index + 1, glyph_to_string(glyph)));
+            }
+        }
+    }
+
+    if !unknown_glyphs.is_empty() {
+        return Err(anyhow!(
+            "Cannot read all letters of {}\n{}",
+            letters,
+            unknown_glyphs.join("")
+        ));
+    }
+
+    Ok(letters)
+}
+
+fn glyph_to_string(glyph: u32) -> String {
+    let mut printout = String::new();
+
+    for bit in (0..GLYPH_WIDTH * GLYPH_HEIGHT).rev() {
+        printout.push(if glyph >> bit & 1 == 1 { '#' } else { '.' });
+
+        if bit % GLYPH_WIDTH == 0 {
+            printout.push('\n');
+        }
+    }
 
     printout
 }
@@ -167,4 +536,115 @@ fold along x=5
 
         Ok(())
     }
+
+    #[test]
+    fn letters() -> Result<()> {
+        let (mut paper, folds) = parse_input_into_paper_and_folds(trim_newlines(EXAMPLE_INPUT))?;
+        for fold in &folds {
+            fold_paper(fold, &mut paper)?;
+        }
+
+        assert_eq!(
+            read_letters(&paper.dots)
+                .err()
+                .map(|error| error.to_string()),
+            Some(String::from("Cannot read dot outside letters: (4, 0)"))
+        );
+
+        // ZKAUCFUC, as in the answers.
+        let dots = [
+            "####.#..#..##..#..#..##..####.#..#..##.",
+            "...#.#.#..#..#.#..#.#..#.#....#..#.#..#",
+            "..#..##...#..#.#..#.#....###..#..#.#...",
+            ".#...#.#..####.#..#.#....#....#..#.#...",
+            "#....#.#..#..#.#..#.#..#.#....#..#.#..#",
+            "####.#..#.#..#..##...##..#.....##...##.",
+        ]
+        .iter()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.bytes()
+                .enumerate()
+                .filter(|&(_, byte)| byte == b'#')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect::<HashSet<_>>();
+        assert_eq!(read_letters(&dots)?, "ZKAUCFUC");
+
+        let mut dots = dots;
+        dots.insert((36, 1));
+        assert_eq!(
+            read_letters(&dots).err().map(|error| error.to_string()),
+            Some(String::from(
+                "Cannot read all letters of ZKAUCFU?\nletter 8:\n.##.\n##.#\n#...\n#...\n#..#\n.##.\n"
+            ))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn off_centre_folds() -> Result<()> {
+        let (mut paper, folds) =
+            parse_input_into_paper_and_folds("0,0\n4,0\n4,1\n\nfold along x=1")?;
+        fold_all(&folds, &mut paper)?;
+
+        assert_eq!(paper_to_string(&paper), "#.#\n#..\n");
+        assert_eq!(paper_to_pbm(&paper), "P1\n3 2\n1 0 1\n1 0 0\n");
+
+        let candidates = unfold_candidates(&paper, &folds, 10)
+            .iter()
+            .map(paper_to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            candidates,
+            vec!["#...#\n....#\n", "..#.#\n....#\n", "#.#.#\n....#\n"]
+        );
+
+        // The same pattern read straight from the input, and one folded about the middle.
+        let (pattern, folds) = parse_input_into_paper_and_folds("0,0\n2,0\n0,1\n\nfold along x=1")?;
+        assert_eq!(unfold_candidates(&pattern, &folds, 10).len(), 3);
+
+        let (pattern, folds) = parse_input_into_paper_and_folds("0,0\n\nfold along x=1")?;
+        let candidates = unfold_candidates(&pattern, &folds, 10)
+            .iter()
+            .map(paper_to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(candidates, vec!["#..\n", "..#\n", "#.#\n"]);
+
+        assert!(fold_paper(&(Axis::Y, 0), &mut paper).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn unfold_round_trip() -> Result<()> {
+        let (mut example, example_folds) =
+            parse_input_into_paper_and_folds(trim_newlines(EXAMPLE_INPUT))?;
+        fold_all(&example_folds, &mut example)?;
+
+        let patterns = [
+            parse_input_into_paper_and_folds(
+                "0,0\n4,1\n\nfold along x=5\nfold along y=5\nfold along y=1",
+            )?,
+            parse_input_into_paper_and_folds("0,0\n2,0\n0,1\n\nfold along x=1\nfold along x=1")?,
+            parse_input_into_paper_and_folds(
+                "1,1\n\nfold along y=3\nfold along x=0\nfold along y=0",
+            )?,
+            (example, example_folds),
+        ];
+
+        // Every candidate folds back into exactly the same dots.
+        for (pattern, folds) in patterns {
+            let candidates = unfold_candidates(&pattern, &folds, 50);
+            assert!(!candidates.is_empty());
+
+            for mut candidate in candidates {
+                fold_all(&folds, &mut candidate)?;
+                assert_eq!(candidate.dots, pattern.dots);
+            }
+        }
+
+        Ok(())
+    }
 }
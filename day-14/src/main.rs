@@ -1,13 +1,26 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::{Display, Formatter},
+    ops::{Add, Mul},
+};
 
 use anyhow::{Result, anyhow};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
 
-use cli::{Part, get_part};
+use cli::{
+    Command, Extra, Part, get_command,
+    matrix::{Matrix, matrix_power},
+};
 
 fn main() {
-    match get_part("inputs/day-14.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-14.txt", &["counts", "analyse", "slice"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -26,16 +39,55 @@ fn part_1(input: &str) -> Result<u32> {
 }
 
 fn part_2(input: &str) -> Result<u64> {
-    // Re-implement using dynamic programming.
-
     let (polymer, rules) = parse_input_into_template_and_rules(input)?;
 
-    let table = count_polymer_elements(&polymer, 40, &rules);
+    let counts = element_counts(&polymer, &rules, 40);
 
-    let mut frequencies = table.values().collect::<Vec<_>>();
-    frequencies.sort();
+    Ok(u64::try_from(most_minus_least_common(&counts))?)
+}
 
-    Ok(frequencies[frequencies.len() - 1] - frequencies[0])
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (polymer, rules) = parse_input_into_template_and_rules(&extra.input)?;
+
+    match (extra.name.as_str(), extra.args.as_slice()) {
+        ("analyse", []) => return Ok(analyse_rules(&polymer, &rules).to_string()),
+        ("analyse", _) => return Err(anyhow!("Usage: analyse")),
+        ("slice", [steps, start, length]) => {
+            let (start, length) = (start.parse::<u64>()?, length.parse::<u64>()?);
+            let end = start.saturating_add(length);
+            let inspector = PolymerInspector::new(&polymer, &rules, steps.parse()?, end)?;
+
+            return Ok(inspector.slice(start, length)? + "\n");
+        }
+        ("slice", _) => return Err(anyhow!("Usage: slice <STEPS> <START> <LENGTH>")),
+        _ => (),
+    }
+
+    let (steps, modulus) = match extra.args.as_slice() {
+        [steps] => (steps.parse()?, None),
+        [steps, modulus] => match modulus.split_once("=") {
+            Some(("modulus", modulus)) => (steps.parse()?, Some(modulus.parse()?)),
+            _ => return Err(anyhow!("Invalid option: {}", modulus)),
+        },
+        _ => return Err(anyhow!("Usage: counts <STEPS> [modulus=<MODULUS>]")),
+    };
+
+    let mut output = String::new();
+
+    if let Some(modulus) = modulus {
+        for (element, count) in element_counts_modulo(&polymer, &rules, steps, modulus)? {
+            output += &format!("{}: {}\n", element as char, count);
+        }
+    } else {
+        let counts = element_counts(&polymer, &rules, steps);
+
+        for (element, count) in &counts {
+            output += &format!("{}: {}\n", *element as char, count);
+        }
+        output += &format!("difference: {}\n", most_minus_least_common(&counts));
+    }
+
+    Ok(output)
 }
 
 type RuleMap = HashMap<(u8, u8), u8>;
@@ -106,71 +158,511 @@ fn table(polymer: &[u8]) -> Vec<u32> {
     element_counts.values().copied().collect()
 }
 
-type FrequencyTable = HashMap<u8, u64>;
+/// Counts each element in the polymer after steps, exactly.
+///
+/// The counts roughly double with every step, so they have about steps * 0.3 digits, and many
+/// thousands of steps take a while. Use [element_counts_modulo] beyond that.
+fn element_counts(template: &[u8], rules: &RuleMap, steps: u64) -> BTreeMap<u8, BigUint> {
+    count_elements_after(
+        template,
+        rules,
+        steps,
+        BigUint::zero(),
+        BigUint::one(),
+        &|value| value,
+    )
+}
 
-type FrequencyTableCache = HashMap<((u8, u8), u8), FrequencyTable>;
+/// Counts each element in the polymer after steps, modulo modulus.
+///
+/// Only the pairs that can occur are counted, and 10^12 steps take about 40 squarings of their
+/// transition matrix.
+fn element_counts_modulo(
+    template: &[u8],
+    rules: &RuleMap,
+    steps: u64,
+    modulus: u64,
+) -> Result<BTreeMap<u8, u64>> {
+    if modulus == 0 {
+        return Err(anyhow!("Invalid modulus: {}", modulus));
+    }
 
-fn count_polymer_elements(polymer: &[u8], steps_left: u8, rules: &RuleMap) -> FrequencyTable {
-    let mut table = HashMap::new();
+    let modulus = u128::from(modulus);
+    let counts = count_elements_after(template, rules, steps, 0, 1 % modulus, &|value| {
+        value % modulus
+    });
+
+    Ok(counts
+        .into_iter()
+        .map(|(element, count)| (element, count as u64))
+        .collect())
+}
 
-    let mut cache = HashMap::new();
-    polymer.windows(2).for_each(|pair| {
-        let pair_table = count_elements((pair[0], pair[1]), steps_left, rules, &mut cache);
+/// Gets the difference between the counts of the most and least common elements.
+fn most_minus_least_common(counts: &BTreeMap<u8, BigUint>) -> BigUint {
+    match (counts.values().max(), counts.values().min()) {
+        (Some(most), Some(least)) => most - least,
+        _ => BigUint::zero(),
+    }
+}
 
-        // Combine everything into table.
-        for (k, v) in pair_table {
-            *table.entry(k).or_default() += v;
+/// Finds the pairs that can occur in the polymer, in order.
+fn reachable_pairs(template: &[u8], rules: &RuleMap) -> Vec<(u8, u8)> {
+    let mut pairs = template
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect::<BTreeSet<_>>();
+    let mut pending = pairs.iter().copied().collect::<Vec<_>>();
+
+    while let Some(pair) = pending.pop() {
+        if let Some(&insert) = rules.get(&pair) {
+            for new_pair in [(pair.0, insert), (insert, pair.1)] {
+                if pairs.insert(new_pair) {
+                    pending.push(new_pair);
+                }
+            }
         }
-    });
+    }
 
-    // Deduct overlaps.
-    polymer[1..polymer.len() - 1].iter().for_each(|overlap| {
-        table.entry(*overlap).and_modify(|v| *v -= 1);
-    });
+    pairs.into_iter().collect()
+}
+
+/// What a rule set does to a template.
+struct RuleAnalysis {
+    /// Pairs that can occur in the polymer, but have no rule, so nothing is ever inserted into them.
+    missing_rules: Vec<(u8, u8)>,
+
+    /// Elements in the rules that can never occur in the polymer.
+    unreachable_elements: Vec<u8>,
 
-    table
+    /// The factor by which the count of each element in the polymer grows with each step, in the
+    /// long run.
+    growth_rates: BTreeMap<u8, f64>,
 }
 
-fn count_elements(
-    pair: (u8, u8),
-    steps_left: u8,
-    rules: &RuleMap,
-    cache: &mut FrequencyTableCache,
-) -> FrequencyTable {
-    // Cache hit.
-    if cache.contains_key(&(pair, steps_left)) {
-        return cache[&(pair, steps_left)].clone();
-    }
-
-    // Base case.
-    if steps_left == 0 {
-        return if pair.0 == pair.1 {
-            HashMap::from([(pair.0, 2)])
-        } else {
-            HashMap::from([(pair.0, 1), (pair.1, 1)])
+impl Display for RuleAnalysis {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let missing_rules = self
+            .missing_rules
+            .iter()
+            .map(|pair| format!("{}{}", pair.0 as char, pair.1 as char))
+            .collect::<Vec<_>>();
+        let unreachable_elements = self
+            .unreachable_elements
+            .iter()
+            .map(|&element| String::from(element as char))
+            .collect::<Vec<_>>();
+        let growth_rates = self
+            .growth_rates
+            .iter()
+            .map(|(&element, rate)| format!("{} {:.3}", element as char, rate))
+            .collect::<Vec<_>>();
+
+        let list = |items: Vec<String>| match items.is_empty() {
+            true => String::from("none"),
+            false => items.join(", "),
         };
+
+        writeln!(f, "pairs without rules: {}", list(missing_rules))?;
+        writeln!(f, "unreachable elements: {}", list(unreachable_elements))?;
+        writeln!(f, "growth per step: {}", list(growth_rates))
     }
+}
+
+/// The steps taken to estimate growth rates, the last of which are averaged over.
+const GROWTH_STEPS: usize = 1000;
 
-    let counts = if rules.contains_key(&pair) {
-        let insert = rules[&pair];
+const GROWTH_AVERAGE_STEPS: usize = 100;
 
-        let mut left_pair = count_elements((pair.0, insert), steps_left - 1, rules, cache);
-        let right_pair = count_elements((insert, pair.1), steps_left - 1, rules, cache);
+fn analyse_rules(template: &[u8], rules: &RuleMap) -> RuleAnalysis {
+    let pairs = reachable_pairs(template, rules);
 
-        // Combine the tables into left_pair.
-        for (k, v) in right_pair {
-            *left_pair.entry(k).or_default() += v;
+    let missing_rules = pairs
+        .iter()
+        .copied()
+        .filter(|pair| !rules.contains_key(pair))
+        .collect();
+
+    let reachable_elements = pairs
+        .iter()
+        .flat_map(|pair| [pair.0, pair.1])
+        .chain(template.iter().copied())
+        .collect::<BTreeSet<_>>();
+    let unreachable_elements = rules
+        .iter()
+        .flat_map(|(pair, &insert)| [pair.0, pair.1, insert])
+        .filter(|element| !reachable_elements.contains(element))
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+
+    RuleAnalysis {
+        missing_rules,
+        unreachable_elements,
+        growth_rates: growth_rates(template, rules, &pairs),
+    }
+}
+
+/// Estimates growth rates by stepping the pair counts as floats, then taking the geometric mean of
+/// the growth over the last [GROWTH_AVERAGE_STEPS], which evens out any oscillation.
+fn growth_rates(template: &[u8], rules: &RuleMap, pairs: &[(u8, u8)]) -> BTreeMap<u8, f64> {
+    let transition = transition_matrix(pairs, rules, 0.0, 1.0);
+
+    let mut pair_counts = vec![0.0; pairs.len()];
+    for pair in template.windows(2) {
+        if let Ok(index) = pairs.binary_search(&(pair[0], pair[1])) {
+            pair_counts[index] += 1.0;
         }
+    }
 
-        // Deduct the overlap element (insert).
-        left_pair.entry(insert).and_modify(|v| *v -= 1);
+    // The last element of the template stays last, and counts once on top of the pairs. Its weight
+    // shrinks along with the pair counts when they are scaled down.
+    let mut last_weight = 1.0;
 
-        left_pair
-    } else {
-        count_elements(pair, steps_left - 1, rules, cache)
+    let element_counts = |pair_counts: &[f64], last_weight: f64| {
+        let mut counts = BTreeMap::new();
+        if let Some(&last) = template.last() {
+            counts.insert(last, last_weight);
+        }
+        for (pair, count) in pairs.iter().zip(pair_counts) {
+            *counts.entry(pair.0).or_insert(0.0) += count;
+        }
+
+        counts
     };
 
-    cache.entry((pair, steps_left)).or_insert(counts).clone()
+    let mut earlier_counts = BTreeMap::new();
+
+    for step in 1..=GROWTH_STEPS {
+        pair_counts = transition
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&pair_counts)
+                    .map(|(factor, count)| factor * count)
+                    .sum()
+            })
+            .collect();
+
+        // Keep the counts in range.
+        let max_count = pair_counts.iter().copied().fold(0.0, f64::max);
+        if max_count > 1e100 {
+            pair_counts.iter_mut().for_each(|count| *count /= 1e100);
+            last_weight /= 1e100;
+            earlier_counts
+                .values_mut()
+                .for_each(|count: &mut f64| *count /= 1e100);
+        }
+
+        if step == GROWTH_STEPS - GROWTH_AVERAGE_STEPS {
+            earlier_counts = element_counts(&pair_counts, last_weight);
+        }
+    }
+
+    element_counts(&pair_counts, last_weight)
+        .into_iter()
+        .filter(|&(_, count)| count > 0.0)
+        .map(|(element, count)| {
+            let rate = (count / earlier_counts[&element]).powf(1.0 / GROWTH_AVERAGE_STEPS as f64);
+
+            (element, rate)
+        })
+        .collect()
+}
+
+/// The most steps for which [PolymerInspector] keeps pair lengths. Beyond that, the capped lengths
+/// must have stopped changing.
+const MAX_LENGTH_STEPS: usize = 1 << 16;
+
+/// Finds elements of the polymer after some steps without building it.
+///
+/// Each pair in the template grows into a part of the polymer, made of the parts that its two new
+/// pairs grow into in one step less. Knowing the length of every such part is enough to walk down
+/// to the element at any index.
+struct PolymerInspector<'a> {
+    template: &'a [u8],
+    rules: &'a RuleMap,
+    steps: u64,
+    pairs: Vec<(u8, u8)>,
+
+    /// The index past the last element that can be looked up.
+    end: u64,
+
+    /// The length of the part each of pairs grows into after each number of steps, excluding the
+    /// last element of the pair. Lengths are capped at end, as any longer part holds every index
+    /// that can be looked up anyway.
+    ///
+    /// Lengths never go down, so they all reach the cap or stop growing at some point. Once they
+    /// stop changing, further steps are left out.
+    lengths: Vec<Vec<u64>>,
+}
+
+impl<'a> PolymerInspector<'a> {
+    /// Prepares to look up the elements before end in the polymer after steps.
+    fn new(template: &'a [u8], rules: &'a RuleMap, steps: u64, end: u64) -> Result<Self> {
+        let pairs = reachable_pairs(template, rules);
+        let mut lengths = vec![vec![1_u64; pairs.len()]];
+
+        while (lengths.len() as u64) <= steps {
+            let previous = &lengths[lengths.len() - 1];
+            let next = pairs
+                .iter()
+                .enumerate()
+                .map(|(index, pair)| match rules.get(pair) {
+                    Some(&insert) => {
+                        let length = |pair| previous[pairs.binary_search(&pair).unwrap_or(index)];
+
+                        length((pair.0, insert))
+                            .saturating_add(length((insert, pair.1)))
+                            .min(end)
+                    }
+                    None => previous[index],
+                })
+                .collect::<Vec<_>>();
+
+            if next == *previous {
+                break;
+            }
+
+            if lengths.len() == MAX_LENGTH_STEPS {
+                return Err(anyhow!(
+                    "Cannot inspect polymer: lengths still change after {} steps",
+                    MAX_LENGTH_STEPS
+                ));
+            }
+
+            lengths.push(next);
+        }
+
+        Ok(PolymerInspector {
+            template,
+            rules,
+            steps,
+            pairs,
+            end,
+            lengths,
+        })
+    }
+
+    /// Gets the length of the part that pairs[pair] grows into after steps.
+    fn length(&self, pair: usize, steps: u64) -> u64 {
+        let steps = steps.min(self.lengths.len() as u64 - 1) as usize;
+
+        self.lengths[steps][pair]
+    }
+
+    fn pair_index(&self, pair: (u8, u8)) -> Result<usize> {
+        self.pairs
+            .binary_search(&pair)
+            .map_err(|_| anyhow!("Cannot find pair: {:?}", pair))
+    }
+
+    /// Gets the element at index.
+    fn element_at(&self, mut index: u64) -> Result<u8> {
+        if index >= self.end {
+            return Err(anyhow!("Cannot find element beyond end: {}", index));
+        }
+
+        // Find the part of the template that index is in.
+        let mut part = None;
+        for pair in self.template.windows(2) {
+            let pair = self.pair_index((pair[0], pair[1]))?;
+            let length = self.length(pair, self.steps);
+
+            if index < length {
+                part = Some(pair);
+                break;
+            }
+
+            index -= length;
+        }
+
+        let Some(mut pair) = part else {
+            return match (index, self.template.last()) {
+                (0, Some(&last)) => Ok(last),
+                _ => Err(anyhow!("Cannot find element past the end of the polymer")),
+            };
+        };
+
+        // Walk down the steps. Once the lengths stop changing, every step is the same, so a repeat
+        // means that the walk is in a cycle, which can be skipped.
+        let stable_steps = self.lengths.len() as u64 - 1;
+        let mut seen = HashMap::new();
+        let mut steps = self.steps;
+
+        while steps > 0 {
+            let (first, second) = self.pairs[pair];
+            let Some(&insert) = self.rules.get(&(first, second)) else {
+                break;
+            };
+
+            if steps > stable_steps
+                && let Some(previous_steps) = seen.insert((pair, index), steps)
+            {
+                let period = previous_steps - steps;
+                steps -= (steps - stable_steps) / period * period;
+                seen.clear();
+
+                continue;
+            }
+
+            let left = self.pair_index((first, insert))?;
+            let left_length = self.length(left, steps - 1);
+
+            if index < left_length {
+                pair = left;
+            } else {
+                index -= left_length;
+                pair = self.pair_index((insert, second))?;
+            }
+
+            steps -= 1;
+        }
+
+        Ok(self.pairs[pair].0)
+    }
+
+    /// Gets length elements from start on.
+    fn slice(&self, start: u64, length: u64) -> Result<String> {
+        (start..start.saturating_add(length))
+            .map(|index| self.element_at(index).map(char::from))
+            .collect()
+    }
+}
+
+/// Builds the matrix that maps the count of each of pairs in one step to those in the next step.
+/// A pair without a rule stays as it is.
+fn transition_matrix<T: Clone + Add<Output = T>>(
+    pairs: &[(u8, u8)],
+    rules: &RuleMap,
+    zero: T,
+    one: T,
+) -> Matrix<T> {
+    let mut matrix = vec![vec![zero; pairs.len()]; pairs.len()];
+
+    for (from, pair) in pairs.iter().enumerate() {
+        let new_pairs = match rules.get(pair) {
+            Some(&insert) => vec![(pair.0, insert), (insert, pair.1)],
+            None => vec![*pair],
+        };
+
+        // Both new pairs may be the same, e.g. for AA -> A.
+        for new_pair in new_pairs {
+            if let Ok(to) = pairs.binary_search(&new_pair) {
+                matrix[to][from] = matrix[to][from].clone() + one.clone();
+            }
+        }
+    }
+
+    matrix
+}
+
+/// Counts each element in the polymer after steps, using reduce to keep values in range. Elements
+/// that are not in the polymer are left out, even if their count reduces to zero.
+fn count_elements_after<T>(
+    template: &[u8],
+    rules: &RuleMap,
+    steps: u64,
+    zero: T,
+    one: T,
+    reduce: &impl Fn(T) -> T,
+) -> BTreeMap<u8, T>
+where
+    T: Clone + Zero + One + Add<Output = T> + Mul<Output = T>,
+{
+    let present = present_elements(template, rules, steps);
+
+    count_reachable_elements_after(template, rules, steps, zero, one, reduce)
+        .into_iter()
+        .filter(|(element, _)| present.contains(element))
+        .collect()
+}
+
+/// Finds the elements in the polymer after steps.
+///
+/// Insertion never removes an element, so these are the template's, plus the insert of every pair
+/// with a rule that is reached before the last step.
+fn present_elements(template: &[u8], rules: &RuleMap, steps: u64) -> BTreeSet<u8> {
+    let mut elements = template.iter().copied().collect::<BTreeSet<_>>();
+    let mut pairs = template
+        .windows(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect::<BTreeSet<_>>();
+
+    // Pairs first reached at each step in turn.
+    let mut frontier = pairs.iter().copied().collect::<Vec<_>>();
+    for _ in 0..steps {
+        if frontier.is_empty() {
+            break;
+        }
+
+        let mut next = Vec::new();
+        for pair in frontier {
+            if let Some(&insert) = rules.get(&pair) {
+                elements.insert(insert);
+
+                for new_pair in [(pair.0, insert), (insert, pair.1)] {
+                    if pairs.insert(new_pair) {
+                        next.push(new_pair);
+                    }
+                }
+            }
+        }
+
+        frontier = next;
+    }
+
+    elements
+}
+
+/// Counts each element that can occur in the polymer after steps, using reduce to keep values in
+/// range.
+///
+/// Every element but the last starts a pair, so the pair counts give the element counts.
+fn count_reachable_elements_after<T>(
+    template: &[u8],
+    rules: &RuleMap,
+    steps: u64,
+    zero: T,
+    one: T,
+    reduce: &impl Fn(T) -> T,
+) -> BTreeMap<u8, T>
+where
+    T: Clone + Zero + One + Add<Output = T> + Mul<Output = T>,
+{
+    let pairs = reachable_pairs(template, rules);
+
+    let mut pair_counts = vec![zero.clone(); pairs.len()];
+    for pair in template.windows(2) {
+        if let Ok(index) = pairs.binary_search(&(pair[0], pair[1])) {
+            pair_counts[index] = reduce(pair_counts[index].clone() + one.clone());
+        }
+    }
+
+    let transition = matrix_power(
+        transition_matrix(&pairs, rules, zero, one.clone()),
+        steps,
+        reduce,
+    );
+
+    let mut counts = BTreeMap::new();
+    if let Some(&last) = template.last() {
+        counts.insert(last, one);
+    }
+
+    for (row, pair) in transition.into_iter().zip(&pairs) {
+        let count = row
+            .into_iter()
+            .zip(pair_counts.iter().cloned())
+            .fold(T::zero(), |acc, (factor, count)| {
+                reduce(acc + factor * count)
+            });
+
+        let total = counts.entry(pair.0).or_insert(T::zero());
+        *total = reduce(total.clone() + count);
+    }
+
+    counts
 }
 
 #[cfg(test)]
@@ -213,4 +705,115 @@ CN -> C
 
         Ok(())
     }
+
+    #[test]
+    fn pair_counts() -> Result<()> {
+        let (polymer, rules) = parse_input_into_template_and_rules(trim_newlines(EXAMPLE_INPUT))?;
+
+        // The same as materialising the polymer.
+        let mut materialised = polymer.clone();
+        for steps in 0..8 {
+            let mut expected = BTreeMap::new();
+            for &element in &materialised {
+                *expected.entry(element).or_insert(BigUint::zero()) += 1_u8;
+            }
+
+            assert_eq!(element_counts(&polymer, &rules, steps), expected);
+            materialised = step(materialised, &rules);
+        }
+
+        let prime = 1_000_000_007;
+        let counts = element_counts(&polymer, &rules, 200);
+        let counts_modulo = element_counts_modulo(&polymer, &rules, 200, prime)?;
+        for (element, count) in counts {
+            assert_eq!(BigUint::from(counts_modulo[&element]), count % prime);
+        }
+        assert!(element_counts_modulo(&polymer, &rules, 10_000_000, prime)?.len() == 4);
+
+        // No rules, so nothing grows.
+        assert_eq!(
+            element_counts(b"AAB", &RuleMap::new(), 1_000_000),
+            BTreeMap::from([(b'A', BigUint::from(2_u8)), (b'B', BigUint::one())])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn rule_analysis() -> Result<()> {
+        let (polymer, rules) = parse_input_into_template_and_rules(trim_newlines(EXAMPLE_INPUT))?;
+        let analysis = analyse_rules(&polymer, &rules);
+
+        assert!(analysis.missing_rules.is_empty());
+        assert!(analysis.unreachable_elements.is_empty());
+        // Not every element grows as fast as the polymer.
+        let counts = element_counts(&polymer, &rules, 500);
+        let previous_counts = element_counts(&polymer, &rules, 499);
+        assert_eq!(analysis.growth_rates.len(), 4);
+        for (element, rate) in &analysis.growth_rates {
+            let ratio = counts[element].to_string().parse::<f64>()?
+                / previous_counts[element].to_string().parse::<f64>()?;
+
+            assert!((rate - ratio).abs() < 1e-6);
+        }
+        assert!((analysis.growth_rates[&b'C'] - 2.0).abs() > 0.1);
+
+        let (polymer, rules) = parse_input_into_template_and_rules("AB\n\nAB -> C\nXY -> Z")?;
+        assert_eq!(
+            analyse_rules(&polymer, &rules).to_string(),
+            "pairs without rules: AC, CB\n\
+             unreachable elements: X, Y, Z\n\
+             growth per step: A 1.000, B 1.000, C 1.000\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn inspection() -> Result<()> {
+        let (polymer, rules) = parse_input_into_template_and_rules(trim_newlines(EXAMPLE_INPUT))?;
+
+        // Without CC -> N, the CN pair grows by one element per step.
+        let mut missing_rule = rules.clone();
+        missing_rule.remove(&(b'C', b'C'));
+
+        // The same as materialising the polymer.
+        for rules in [&rules, &missing_rule] {
+            let mut materialised = polymer.clone();
+            for steps in 0..10 {
+                let length = materialised.len() as u64;
+                let inspector = PolymerInspector::new(&polymer, rules, steps, length + 1)?;
+
+                assert_eq!(inspector.slice(0, length)?.as_bytes(), materialised);
+                assert!(inspector.element_at(length).is_err());
+
+                materialised = step(materialised, rules);
+            }
+        }
+
+        // Far beyond what could be built.
+        let inspector = PolymerInspector::new(&polymer, &rules, 1_000_000_000_000, 1_000_005)?;
+        assert_eq!(inspector.slice(0, 1)?, "N");
+        assert_eq!(inspector.slice(1_000_000, 5)?.len(), 5);
+        assert!(inspector.slice(1_000_000, 6).is_err());
+
+        let inspector = PolymerInspector::new(&polymer, &missing_rule, 1_000_000, 5)?;
+        assert_eq!(
+            inspector.slice(0, 5)?,
+            PolymerInspector::new(&polymer, &missing_rule, 1_000_000, 1000)?.slice(0, 5)?
+        );
+
+        // Growth by one element per step never settles into a cycle, but the lengths still reach
+        // the cap.
+        let (polymer, rules) = parse_input_into_template_and_rules("BA\n\nBA -> B")?;
+        let inspector = PolymerInspector::new(&polymer, &rules, 5, 8)?;
+        assert_eq!(inspector.slice(0, 7)?, "BBBBBBA");
+        assert!(inspector.slice(0, 8).is_err());
+
+        let inspector = PolymerInspector::new(&polymer, &rules, 1_000_000_000_000, 7)?;
+        assert_eq!(inspector.slice(0, 7)?, "BBBBBBB");
+        assert!(PolymerInspector::new(&polymer, &rules, 1_000_000_000_000, 1_000_000).is_err());
+
+        Ok(())
+    }
 }
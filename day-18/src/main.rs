@@ -182,10 +182,10 @@ fn explode(pair: &mut Vec<Symbol>) -> Result<bool> {
 fn split(pair: &mut Vec<Symbol>) -> Result<bool> {
     // Find a splitting number.
     let to_split = pair.iter().position(|symbol| {
-        if let Symbol::Number(number) = symbol {
-            if *number > 9 {
-                return true;
-            }
+        if let Symbol::Number(number) = symbol
+            && *number > 9
+        {
+            return true;
         }
 
         false
@@ -1,66 +1,156 @@
+use std::str::FromStr;
+
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-2.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-2.txt", &["trajectory"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
-fn part_1(input: &str) -> Result<u32> {
-    let coord = input.lines().try_fold((0, 0), |acc, line| {
-        // Assumption: depth does not go below 0.
-
-        let (prev_x, prev_y) = acc;
-
-        match parse_value(line) {
-            Ok(x) if line.starts_with("forward") => Ok((prev_x + x, prev_y)),
-            Ok(y) if line.starts_with("down") => Ok((prev_x, prev_y + y)),
-            Ok(y) if line.starts_with("up") => Ok((
-                prev_x,
-                prev_y.checked_sub(y).ok_or(anyhow!("depth underflow"))?,
-            )),
-            Err(error) => Err(anyhow!("Unable to parse value: {}", error)),
-            Ok(_) => Err(anyhow!("Unhandled line: {}", line)),
+fn part_1(input: &str) -> Result<i64> {
+    let position = final_position(input, Position::steer)?;
+
+    Ok(position.horizontal * position.depth)
+}
+
+fn part_2(input: &str) -> Result<i64> {
+    let position = final_position(input, Position::steer_with_aim)?;
+
+    Ok(position.horizontal * position.depth)
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    // The only extra is "trajectory", with an optional model number.
+    let steer: fn(Position, Instruction) -> Position = match extra.args.as_slice() {
+        [] => Position::steer_with_aim,
+        [model] if model == "1" => Position::steer,
+        [model] if model == "2" => Position::steer_with_aim,
+        _ => return Err(anyhow!("Usage: trajectory [1|2]")),
+    };
+
+    Ok(trajectory_to_csv(&trajectory(&extra.input, steer)?))
+}
+
+#[derive(Clone, Copy, Default)]
+struct Position {
+    horizontal: i64,
+    depth: i64,
+    aim: i64,
+}
+
+impl Position {
+    /// Applies an [Instruction] as described in part 1. aim is left untouched.
+    fn steer(self, instruction: Instruction) -> Self {
+        match instruction {
+            Instruction::Forward(x) => Position {
+                horizontal: self.horizontal + x,
+                ..self
+            },
+            Instruction::Down(y) => Position {
+                depth: self.depth + y,
+                ..self
+            },
+            Instruction::Up(y) => Position {
+                depth: self.depth - y,
+                ..self
+            },
+        }
+    }
+
+    /// Applies an [Instruction] as described in part 2.
+    fn steer_with_aim(self, instruction: Instruction) -> Self {
+        match instruction {
+            Instruction::Forward(x) => Position {
+                horizontal: self.horizontal + x,
+                depth: self.depth + x * self.aim,
+                ..self
+            },
+            Instruction::Down(aim) => Position {
+                aim: self.aim + aim,
+                ..self
+            },
+            Instruction::Up(aim) => Position {
+                aim: self.aim - aim,
+                ..self
+            },
         }
-    })?;
+    }
+}
 
-    Ok(coord.0 * coord.1)
+#[derive(Clone, Copy)]
+enum Instruction {
+    Forward(i64),
+    Down(i64),
+    Up(i64),
 }
 
-fn part_2(input: &str) -> Result<u32> {
-    let coord = input.lines().try_fold((0, 0, 0), |acc, line| {
-        // Assumption: aim does not go below 0.
-
-        let (prev_x, prev_y, prev_aim) = acc;
-
-        match parse_value(line) {
-            Ok(x) if line.starts_with("forward") => {
-                Ok((prev_x + x, prev_y + x * prev_aim, prev_aim))
-            }
-            Ok(aim) if line.starts_with("down") => Ok((prev_x, prev_y, prev_aim + aim)),
-            Ok(aim) if line.starts_with("up") => Ok((
-                prev_x,
-                prev_y,
-                prev_aim.checked_sub(aim).ok_or(anyhow!("aim underflow"))?,
-            )),
-            Err(error) => Err(anyhow!("Unable to parse value: {}", error)),
-            Ok(_) => Err(anyhow!("Unhandled line: {}", line)),
+impl FromStr for Instruction {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let Some((direction, value)) = s.split_once(" ") else {
+            return Err(anyhow!("Cannot split s: {}", s));
+        };
+
+        let value = value
+            .parse()
+            .map_err(|error| anyhow!("Unable to parse value: {}", error))?;
+
+        match direction {
+            "forward" => Ok(Instruction::Forward(value)),
+            "down" => Ok(Instruction::Down(value)),
+            "up" => Ok(Instruction::Up(value)),
+            _ => Err(anyhow!("Unhandled line: {}", s)),
         }
-    })?;
+    }
+}
+
+/// Follows the course in input, returning the [Position] after each step. The first entry is the
+/// starting [Position].
+fn trajectory(input: &str, steer: fn(Position, Instruction) -> Position) -> Result<Vec<Position>> {
+    let mut positions = vec![Position::default()];
+
+    for line in input.lines() {
+        let instruction = Instruction::from_str(line)?;
+        let position = positions[positions.len() - 1];
+
+        positions.push(steer(position, instruction));
+    }
 
-    Ok(coord.0 * coord.1)
+    Ok(positions)
 }
 
-fn parse_value(line: &str) -> Result<u32> {
-    let Some((_, value)) = line.split_once(" ") else {
-        return Err(anyhow!("Cannot split line: {}", line));
-    };
+/// Follows the course in input, returning only the final [Position].
+fn final_position(input: &str, steer: fn(Position, Instruction) -> Position) -> Result<Position> {
+    input
+        .lines()
+        .map(Instruction::from_str)
+        .try_fold(Position::default(), |position, instruction| {
+            Ok(steer(position, instruction?))
+        })
+}
+
+/// Renders a trajectory as CSV with a header row.
+fn trajectory_to_csv(positions: &[Position]) -> String {
+    let mut csv = String::from("step,horizontal,depth,aim\n");
+
+    for (step, position) in positions.iter().enumerate() {
+        csv += &format!(
+            "{},{},{},{}\n",
+            step, position.horizontal, position.depth, position.aim
+        );
+    }
 
-    Ok(value.parse()?)
+    csv
 }
 
 #[cfg(test)]
@@ -91,4 +181,34 @@ forward 2
 
         Ok(())
     }
+
+    #[test]
+    fn trajectory_csv() -> Result<()> {
+        let csv = trajectory_to_csv(&trajectory(
+            trim_newlines(EXAMPLE_INPUT),
+            Position::steer_with_aim,
+        )?);
+
+        assert_eq!(
+            csv,
+            "step,horizontal,depth,aim\n\
+             0,0,0,0\n\
+             1,5,0,0\n\
+             2,5,0,5\n\
+             3,13,40,5\n\
+             4,13,40,2\n\
+             5,13,40,10\n\
+             6,15,60,10\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn above_surface() -> Result<()> {
+        assert_eq!(part_1("forward 2\nup 3")?, -6);
+        assert_eq!(part_2("up 1\nforward 4")?, -16);
+
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, str::FromStr};
 
 use anyhow::{Result, anyhow};
 
@@ -12,96 +12,237 @@ fn main() {
     }
 }
 
-fn part_1(input: &str) -> Result<u32> {
-    let lines = input.lines().collect::<Vec<_>>();
+fn part_1(input: &str) -> Result<u128> {
+    let report = Report::from_str(input)?;
 
-    let var_size = lines.first().map_or(0, |first_line| first_line.len());
-    let var_count = lines.len();
+    let gamma = report.select_bits(&Criteria::GAMMA);
+    let epsilon = report.select_bits(&Criteria::EPSILON);
 
-    assert!(var_size > 0);
-    assert!(var_count > 0);
+    gamma
+        .checked_mul(epsilon)
+        .ok_or(anyhow!("Power consumption overflow"))
+}
+
+fn part_2(input: &str) -> Result<u128> {
+    let trie = ReportTrie::from(&Report::from_str(input)?);
+
+    let oxygen_generator_rating = trie.rating(&Criteria::OXYGEN_GENERATOR)?;
+    let co2_scrubber_rating = trie.rating(&Criteria::CO2_SCRUBBER)?;
+
+    oxygen_generator_rating
+        .checked_mul(co2_scrubber_rating)
+        .ok_or(anyhow!("Life support rating overflow"))
+}
+
+/// A diagnostic report of binary numbers that all have the same width, up to 128 bits.
+struct Report {
+    width: usize,
+    numbers: Vec<u128>,
+}
+
+impl FromStr for Report {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let width = s.lines().next().map_or(0, str::len);
+        if width == 0 || width > u128::BITS as usize {
+            return Err(anyhow!("Invalid width: {}", width));
+        }
 
-    let count_ones = lines.into_iter().fold(vec![0; var_size], |mut acc, line| {
-        line.bytes().enumerate().for_each(|(col, byte)| {
-            if byte == b'1' {
-                acc[col] += 1;
+        let numbers = s
+            .lines()
+            .map(|line| {
+                if line.len() != width {
+                    return Err(anyhow!("Mismatched width: {}", line));
+                }
+
+                Ok(u128::from_str_radix(line, 2)?)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Report { width, numbers })
+    }
+}
+
+impl Report {
+    /// Counts the ones in each column. Column 0 is the most significant bit.
+    fn count_ones(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.width];
+
+        for &number in &self.numbers {
+            // Visit only the set bits, clearing the lowest one each time.
+            let mut bits = number;
+            while bits != 0 {
+                counts[self.width - 1 - bits.trailing_zeros() as usize] += 1;
+                bits &= bits - 1;
             }
-        });
+        }
+
+        counts
+    }
 
-        acc
-    });
+    /// Assembles a number by applying criteria to every column of the whole report.
+    fn select_bits(&self, criteria: &impl BitCriteria) -> u128 {
+        let count = self.numbers.len();
 
-    let Some(gamma) = count_ones
-        .into_iter()
-        .map(|count| if count > var_count - count { 1 } else { 0 })
-        .reduce(|acc, bit| (acc << 1) + bit)
-    else {
-        return Err(anyhow!("Cannot assemble gamma"));
-    };
+        self.count_ones().into_iter().fold(0, |acc, ones| {
+            (acc << 1) | u128::from(criteria.keep_bit(ones, count - ones))
+        })
+    }
+
+    /// Filters the report down to a single number, applying criteria to each column in turn
+    /// against the remaining numbers only.
+    ///
+    /// This rescans the remaining numbers for every column. Use [ReportTrie::rating] instead.
+    #[cfg(test)]
+    fn filter_to_rating(&self, criteria: &impl BitCriteria) -> Result<u128> {
+        let mut numbers = self.numbers.clone();
+
+        for column in 0..self.width {
+            if numbers.len() == 1 {
+                break;
+            }
+
+            let mask = 1 << (self.width - 1 - column);
+            let ones = numbers.iter().filter(|&&number| number & mask != 0).count();
+            let keep = if criteria.keep_bit(ones, numbers.len() - ones) {
+                mask
+            } else {
+                0
+            };
 
-    let epsilon = u32::from_str_radix(&"1".repeat(var_size), 2)? ^ gamma;
+            numbers.retain(|&number| number & mask == keep);
+        }
 
-    Ok(gamma * epsilon)
+        match numbers.as_slice() {
+            [rating] => Ok(*rating),
+            [] => Err(anyhow!("All numbers filtered out")),
+            _ => Err(anyhow!("Cannot reduce to a single number")),
+        }
+    }
 }
 
-fn part_2(input: &str) -> Result<u32> {
-    let lines = input.lines().collect::<Vec<_>>();
+/// A binary trie of the numbers in a [Report], most significant bit first.
+struct ReportTrie {
+    width: usize,
+
+    /// All nodes, with the root at index 0.
+    nodes: Vec<TrieNode>,
+}
 
-    let oxygen_generator_rating = u32::from_str_radix(reduce_numbers_to_rating(&lines, 0, 1)?, 2)?;
-    let co2_scrubber_rating = u32::from_str_radix(reduce_numbers_to_rating(&lines, 0, 0)?, 2)?;
+#[derive(Default)]
+struct TrieNode {
+    /// Number of report numbers passing through this node.
+    count: usize,
 
-    Ok(oxygen_generator_rating * co2_scrubber_rating)
+    /// Indices of the child nodes for bit values 0 and 1.
+    children: [Option<usize>; 2],
 }
 
-/// Filters the list input numbers down to a single one.
-///
-/// Use tie_breaker to determine how the list is filtered:
-/// - 0 will favor least common value at bit_to_check, using 0 in case of a tie.
-/// - 1 will favor most common value at bit_to_check, using 1 in case of a tie.
-fn reduce_numbers_to_rating<'a>(
-    numbers: &[&'a str],
-    bit_to_check: usize,
-    tie_breaker: u8,
-) -> Result<&'a str> {
-    assert!(tie_breaker == 0 || tie_breaker == 1);
-    assert!(numbers.len() > 1);
-    assert!(bit_to_check < numbers[0].len());
-
-    let ones_count = numbers
-        .iter()
-        .filter(|line| line.as_bytes()[bit_to_check] == b'1')
-        .count();
-
-    let value_to_match = match (tie_breaker, ones_count.cmp(&(numbers.len() - ones_count))) {
-        (0, Ordering::Equal) => b'0',
-        (0, Ordering::Greater) => b'0',
-        (0, Ordering::Less) => b'1',
-        (1, Ordering::Equal) => b'1',
-        (1, Ordering::Greater) => b'1',
-        (1, Ordering::Less) => b'0',
-        (x, _) => return Err(anyhow!("Invalid tie-breaker: {}", x)),
-    };
-
-    let filtered_numbers = numbers
-        .iter()
-        .filter(|number| number.as_bytes()[bit_to_check] == value_to_match)
-        .copied()
-        .collect::<Vec<_>>();
-
-    if filtered_numbers.len() == 1 {
-        return Ok(filtered_numbers[0]);
+impl From<&Report> for ReportTrie {
+    fn from(report: &Report) -> Self {
+        let mut nodes = vec![TrieNode::default()];
+
+        for &number in &report.numbers {
+            let mut node = 0;
+            nodes[node].count += 1;
+
+            for bit in (0..report.width)
+                .rev()
+                .map(|bit| (number >> bit) as usize & 1)
+            {
+                node = match nodes[node].children[bit] {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(TrieNode::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children[bit] = Some(child);
+                        child
+                    }
+                };
+                nodes[node].count += 1;
+            }
+        }
+
+        ReportTrie {
+            width: report.width,
+            nodes,
+        }
     }
+}
+
+impl ReportTrie {
+    /// Finds the single number that remains after filtering by criteria column by column.
+    ///
+    /// Same result as [Report::filter_to_rating], but walks down the trie once instead of
+    /// rescanning the report.
+    fn rating(&self, criteria: &impl BitCriteria) -> Result<u128> {
+        let count = |child: Option<usize>| child.map_or(0, |child| self.nodes[child].count);
+
+        let mut node = &self.nodes[0];
+        let mut rating = 0;
+
+        for column in 0..self.width {
+            let ones = count(node.children[1]);
+            let zeros = count(node.children[0]);
+
+            // Once a single number remains, just follow it down.
+            let bit = if node.count == 1 {
+                ones == 1
+            } else {
+                criteria.keep_bit(ones, zeros)
+            };
 
-    if filtered_numbers.is_empty() {
-        return Err(anyhow!("All numbers filtered out at bit: {}", bit_to_check));
+            let Some(child) = node.children[usize::from(bit)] else {
+                return Err(anyhow!("All numbers filtered out at column: {}", column));
+            };
+
+            node = &self.nodes[child];
+            rating = (rating << 1) | u128::from(bit);
+        }
+
+        if node.count > 1 {
+            return Err(anyhow!("Cannot reduce to a single number"));
+        }
+
+        Ok(rating)
     }
+}
 
-    reduce_numbers_to_rating(&filtered_numbers, bit_to_check + 1, tie_breaker)
+/// Decides which value to keep for a column.
+trait BitCriteria {
+    /// Returns true to keep 1, false to keep 0, given the number of ones and zeros in the column.
+    fn keep_bit(&self, ones: usize, zeros: usize) -> bool;
+}
+
+/// Keeps the most or least common value, using tie_breaker when both are equally common.
+enum Criteria {
+    MostCommon { tie_breaker: bool },
+    LeastCommon { tie_breaker: bool },
+}
+
+impl Criteria {
+    const GAMMA: Criteria = Criteria::MostCommon { tie_breaker: false };
+    const EPSILON: Criteria = Criteria::LeastCommon { tie_breaker: true };
+    const OXYGEN_GENERATOR: Criteria = Criteria::MostCommon { tie_breaker: true };
+    const CO2_SCRUBBER: Criteria = Criteria::LeastCommon { tie_breaker: false };
+}
+
+impl BitCriteria for Criteria {
+    fn keep_bit(&self, ones: usize, zeros: usize) -> bool {
+        match (self, ones.cmp(&zeros)) {
+            (Criteria::MostCommon { tie_breaker }, Ordering::Equal)
+            | (Criteria::LeastCommon { tie_breaker }, Ordering::Equal) => *tie_breaker,
+            (Criteria::MostCommon { .. }, ordering) => ordering == Ordering::Greater,
+            (Criteria::LeastCommon { .. }, ordering) => ordering == Ordering::Less,
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use cli::trim_newlines;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
 
     use super::*;
 
@@ -133,4 +274,76 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn wide_report() -> Result<()> {
+        let report = Report::from_str(&format!(
+            "1{}\n0{}\n1{}",
+            "0".repeat(99),
+            "1".repeat(99),
+            "1".repeat(99)
+        ))?;
+
+        assert_eq!(report.width, 100);
+        assert_eq!(report.select_bits(&Criteria::GAMMA), (1 << 100) - 1);
+        assert_eq!(
+            report.filter_to_rating(&Criteria::CO2_SCRUBBER)?,
+            (1 << 99) - 1
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_criteria() -> Result<()> {
+        /// Always keeps the value with any ones, regardless of commonality.
+        struct AnyOne;
+
+        impl BitCriteria for AnyOne {
+            fn keep_bit(&self, ones: usize, _: usize) -> bool {
+                ones > 0
+            }
+        }
+
+        let report = Report::from_str(trim_newlines(EXAMPLE_INPUT))?;
+
+        assert_eq!(report.select_bits(&AnyOne), 0b11111);
+        assert_eq!(report.filter_to_rating(&AnyOne)?, 0b11110);
+        assert_eq!(ReportTrie::from(&report).rating(&AnyOne)?, 0b11110);
+
+        Ok(())
+    }
+
+    #[test]
+    fn trie_matches_filter() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut next_line = |width: usize| {
+            (0..width)
+                .map(|_| if rng.random_bool(0.5) { '1' } else { '0' })
+                .collect::<String>()
+        };
+
+        for (width, count) in [(5, 12), (12, 1000), (40, 500), (128, 64)] {
+            let input = (0..count)
+                .map(|_| next_line(width))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let report = Report::from_str(&input)?;
+            let trie = ReportTrie::from(&report);
+
+            for criteria in [
+                Criteria::GAMMA,
+                Criteria::EPSILON,
+                Criteria::OXYGEN_GENERATOR,
+                Criteria::CO2_SCRUBBER,
+            ] {
+                assert_eq!(
+                    trie.rating(&criteria).ok(),
+                    report.filter_to_rating(&criteria).ok()
+                );
+            }
+        }
+
+        Ok(())
+    }
 }
@@ -1,44 +1,199 @@
-use std::num::ParseIntError;
+use std::{num::ParseIntError, str::FromStr};
 
 use anyhow::{Result, anyhow};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command_with_inputless};
 
 fn main() {
-    match get_part("inputs/day-4.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command_with_inputless("inputs/day-4.txt", &["tournament"], &["generate"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: &str) -> Result<u32> {
-    let Some((sequence_part, bingo_cards_part)) = input.split_once("\n\n") else {
-        return Err(anyhow!(
-            "Cannot divide input into sequence and bingo cards: {}",
-            input
-        ));
+    let (sequence, bingo_cards) = parse_input(input)?;
+
+    let Some(win) = play_tournament(&sequence, bingo_cards, Rules::default())?
+        .wins
+        .first()
+        .copied()
+    else {
+        return Err(anyhow!("Cannot reach bingo"));
     };
 
-    let sequence = parse_sequence(sequence_part)?;
+    Ok(win.score)
+}
 
-    let mut bingo_cards = bingo_cards_part
-        .split_terminator("\n\n")
-        .map(parse_bingo_card)
-        .collect::<Result<Vec<_>, _>>()?;
+fn part_2(input: &str) -> Result<u32> {
+    let (sequence, bingo_cards) = parse_input(input)?;
+
+    let Some(win) = play_tournament(&sequence, bingo_cards, Rules::default())?
+        .wins
+        .last()
+        .copied()
+    else {
+        return Err(anyhow!("Cannot reach bingo"));
+    };
+
+    Ok(win.score)
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    match (extra.name.as_str(), extra.args.as_slice()) {
+        ("tournament", []) => tournament_report(&extra.input, Rules::default()),
+        ("tournament", [rules]) => tournament_report(&extra.input, rules.parse()?),
+        ("generate", [rows, columns, cards, seed]) => generate_input(
+            &mut StdRng::seed_from_u64(seed.parse()?),
+            rows.parse()?,
+            columns.parse()?,
+            cards.parse()?,
+        ),
+        ("tournament", _) => Err(anyhow!(
+            "Usage: tournament [lines|diagonals|full-house|corners]"
+        )),
+        _ => Err(anyhow!("Usage: generate <ROWS> <COLUMNS> <CARDS> <SEED>")),
+    }
+}
+
+/// Describes every card's win in finishing order, then the cards that never win.
+fn tournament_report(input: &str, rules: Rules) -> Result<String> {
+    let (sequence, bingo_cards) = parse_input(input)?;
+    let tournament = play_tournament(&sequence, bingo_cards, rules)?;
+
+    let mut output = String::new();
+    for (place, win) in tournament.wins.iter().enumerate() {
+        output += &format!(
+            "#{}: card {} on turn {} with {} ({:?}), score {}\n",
+            place + 1,
+            win.card,
+            win.turn,
+            win.number,
+            win.pattern,
+            win.score
+        );
+    }
+    for card in tournament.losers {
+        output += &format!("-: card {card} never wins\n");
+    }
+
+    Ok(output)
+}
 
-    for number in sequence {
-        for bingo_card in &mut bingo_cards {
-            if bingo(number, bingo_card)? {
-                return Ok(bingo_card_value(bingo_card) * number);
+type BingoCard = Vec<Vec<Option<u32>>>;
+
+/// Which patterns of marked numbers count as bingo.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Rules {
+    /// Any row or column. With diagonals, also either diagonal of a square card.
+    Lines { diagonals: bool },
+
+    /// Every number on the card.
+    FullHouse,
+
+    /// The four corners of the card.
+    Corners,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules::Lines { diagonals: false }
+    }
+}
+
+impl FromStr for Rules {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lines" => Ok(Rules::Lines { diagonals: false }),
+            "diagonals" => Ok(Rules::Lines { diagonals: true }),
+            "full-house" => Ok(Rules::FullHouse),
+            "corners" => Ok(Rules::Corners),
+            _ => Err(anyhow!("Invalid rules: {}", s)),
+        }
+    }
+}
+
+/// A completed pattern on a [BingoCard].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Pattern {
+    Row(usize),
+    Column(usize),
+
+    /// From top left to bottom right.
+    Diagonal,
+
+    /// From top right to bottom left.
+    AntiDiagonal,
+
+    FullHouse,
+    Corners,
+}
+
+/// The moment a [BingoCard] reaches bingo. card and turn are indices into the input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Win {
+    card: usize,
+    turn: usize,
+    number: u32,
+    pattern: Pattern,
+    score: u32,
+}
+
+struct Tournament {
+    /// Wins in finishing order. Cards winning on the same turn are ordered by card index.
+    wins: Vec<Win>,
+
+    /// Indices of the cards that never reach bingo.
+    losers: Vec<usize>,
+}
+
+/// Plays the whole sequence on all bingo_cards, recording each card's first bingo.
+fn play_tournament(
+    sequence: &[u32],
+    mut bingo_cards: Vec<BingoCard>,
+    rules: Rules,
+) -> Result<Tournament> {
+    let mut wins = Vec::new();
+    let mut playing = (0..bingo_cards.len()).collect::<Vec<_>>();
+
+    for (turn, &number) in sequence.iter().enumerate() {
+        if playing.is_empty() {
+            break;
+        }
+
+        let mut still_playing = Vec::with_capacity(playing.len());
+
+        for card in playing {
+            match bingo(number, &mut bingo_cards[card], rules)? {
+                Some(pattern) => wins.push(Win {
+                    card,
+                    turn,
+                    number,
+                    pattern,
+                    score: bingo_card_value(&bingo_cards[card]) * number,
+                }),
+                None => still_playing.push(card),
             }
         }
+
+        playing = still_playing;
     }
 
-    Err(anyhow!("Cannot reach bingo"))
+    Ok(Tournament {
+        wins,
+        losers: playing,
+    })
 }
 
-fn part_2(input: &str) -> Result<u32> {
+fn parse_input(input: &str) -> Result<(Vec<u32>, Vec<BingoCard>)> {
     let Some((sequence_part, bingo_cards_part)) = input.split_once("\n\n") else {
         return Err(anyhow!(
             "Cannot divide input into sequence and bingo cards: {}",
@@ -48,38 +203,20 @@ fn part_2(input: &str) -> Result<u32> {
 
     let sequence = parse_sequence(sequence_part)?;
 
-    let mut bingo_cards = bingo_cards_part
+    let bingo_cards = bingo_cards_part
         .split_terminator("\n\n")
         .map(parse_bingo_card)
-        .collect::<Result<Vec<_>, _>>()?;
-
-    for number in sequence {
-        let len = bingo_cards.len();
-
-        // Reversing the index in case multiple cards need to be removed.
-        for index in (0..len).rev() {
-            let cards_left = bingo_cards.len(); // in case other cards are removed
-            let bingo_card = &mut bingo_cards[index];
-
-            if bingo(number, bingo_card)? {
-                if cards_left == 1 {
-                    return Ok(bingo_card_value(bingo_card) * number);
-                }
-
-                bingo_cards.remove(index);
-            }
-        }
-    }
+        .collect::<Result<Vec<_>>>()?;
 
-    Err(anyhow!("Cannot reach bingo"))
+    Ok((sequence, bingo_cards))
 }
 
 fn parse_sequence(input: &str) -> Result<Vec<u32>, ParseIntError> {
     input.split_terminator(",").map(str::parse).collect()
 }
 
-fn parse_bingo_card(input: &str) -> Result<Vec<Vec<Option<u32>>>, ParseIntError> {
-    input
+fn parse_bingo_card(input: &str) -> Result<BingoCard> {
+    let bingo_card = input
         .lines()
         .map(|line| {
             line.split_ascii_whitespace()
@@ -90,38 +227,86 @@ fn parse_bingo_card(input: &str) -> Result<Vec<Vec<Option<u32>>>, ParseIntError>
                 .map(Result::Ok)
                 .collect()
         })
-        .collect::<Result<Vec<_>, _>>()
+        .collect::<Result<Vec<Vec<_>>, ParseIntError>>()?;
+
+    let columns = bingo_card.first().map_or(0, Vec::len);
+    if columns == 0 || bingo_card.iter().any(|row| row.len() != columns) {
+        return Err(anyhow!("Bingo card is not rectangular: {}", input));
+    }
+
+    Ok(bingo_card)
 }
 
-/// Checks a card for number match and bingo.
-fn bingo(number: u32, bingo_card: &mut [Vec<Option<u32>>]) -> Result<bool> {
+/// Checks a card for number match and bingo, returning the completed [Pattern] if any.
+fn bingo(
+    number: u32,
+    bingo_card: &mut [Vec<Option<u32>>],
+    rules: Rules,
+) -> Result<Option<Pattern>> {
     // Check for match.
-    if let Some(row) = bingo_card
+    let Some(row) = bingo_card
         .iter()
         .position(|row| row.contains(&Some(number)))
-    {
-        let Some(col) = bingo_card[row]
-            .iter()
-            .position(|value| value == &Some(number))
-        else {
-            return Err(anyhow!("Cannot retrieve number position"));
-        };
+    else {
+        return Ok(None);
+    };
+    let Some(col) = bingo_card[row]
+        .iter()
+        .position(|value| value == &Some(number))
+    else {
+        return Err(anyhow!("Cannot retrieve number position"));
+    };
 
-        // Mark it.
-        bingo_card[row][col] = None;
+    // Mark it.
+    bingo_card[row][col] = None;
 
-        // Check row for bingo.
-        if bingo_card[row].iter().all(|value| value.is_none()) {
-            return Ok(true);
-        }
+    let rows = bingo_card.len();
+    let cols = bingo_card[0].len();
+
+    match rules {
+        Rules::Lines { diagonals } => {
+            // Check row for bingo.
+            if bingo_card[row].iter().all(|value| value.is_none()) {
+                return Ok(Some(Pattern::Row(row)));
+            }
+
+            // Check col for bingo.
+            if bingo_card.iter().all(|row| row[col].is_none()) {
+                return Ok(Some(Pattern::Column(col)));
+            }
+
+            // Check diagonals for bingo, only on square cards.
+            if diagonals && rows == cols {
+                if row == col && (0..rows).all(|index| bingo_card[index][index].is_none()) {
+                    return Ok(Some(Pattern::Diagonal));
+                }
 
-        // Check col for bingo.
-        if bingo_card.iter().all(|row| row[col].is_none()) {
-            return Ok(true);
+                if row + col == rows - 1
+                    && (0..rows).all(|index| bingo_card[index][rows - 1 - index].is_none())
+                {
+                    return Ok(Some(Pattern::AntiDiagonal));
+                }
+            }
+        }
+        Rules::FullHouse => {
+            if bingo_card.iter().flatten().all(Option::is_none) {
+                return Ok(Some(Pattern::FullHouse));
+            }
+        }
+        Rules::Corners => {
+            let corners = [(0, 0), (0, cols - 1), (rows - 1, 0), (rows - 1, cols - 1)];
+
+            if corners.contains(&(row, col))
+                && corners
+                    .iter()
+                    .all(|&(row, col)| bingo_card[row][col].is_none())
+            {
+                return Ok(Some(Pattern::Corners));
+            }
         }
     }
 
-    Ok(false)
+    Ok(None)
 }
 
 /// Sums up the remaining numbers in bingo_card.
@@ -132,6 +317,52 @@ fn bingo_card_value(bingo_card: &[Vec<Option<u32>>]) -> u32 {
         .sum()
 }
 
+/// Generates a puzzle input of card_count random rows x columns cards.
+///
+/// Each card holds distinct numbers from a pool of at least 100 numbers, and the sequence calls
+/// every number in the pool once, so every card eventually wins under any [Rules].
+fn generate_input(
+    rng: &mut impl Rng,
+    rows: usize,
+    columns: usize,
+    card_count: usize,
+) -> Result<String> {
+    if rows == 0 || columns == 0 {
+        return Err(anyhow!("Invalid card size: {}x{}", rows, columns));
+    }
+
+    let pool_size = u32::try_from(usize::max(100, rows * columns * 2))?;
+    let mut pool = (0..pool_size).collect::<Vec<_>>();
+    let number_width = (pool_size - 1).to_string().len();
+
+    let mut cards = Vec::with_capacity(card_count);
+    for _ in 0..card_count {
+        pool.shuffle(rng);
+
+        cards.push(
+            pool[..rows * columns]
+                .chunks(columns)
+                .map(|row| {
+                    row.iter()
+                        .map(|number| format!("{number:>number_width$}"))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        );
+    }
+
+    pool.shuffle(rng);
+    let sequence = pool
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    Ok(format!("{}\n\n{}\n", sequence, cards.join("\n\n")))
+}
+
 #[cfg(test)]
 mod tests {
     use cli::trim_newlines;
@@ -173,4 +404,98 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn tournament() -> Result<()> {
+        let (sequence, mut bingo_cards) = parse_input(trim_newlines(EXAMPLE_INPUT))?;
+
+        // A card that cannot win with the example sequence.
+        bingo_cards.push(parse_bingo_card(
+            "99 13 17 11  0\n 8 99 23  4 24\n21  9 99 16  7\n 6 10  3 99  5\n 1 12 20 15 99",
+        )?);
+
+        let tournament = play_tournament(&sequence, bingo_cards, Rules::default())?;
+
+        assert_eq!(
+            tournament.wins,
+            vec![
+                Win {
+                    card: 2,
+                    turn: 11,
+                    number: 24,
+                    pattern: Pattern::Row(0),
+                    score: 4512
+                },
+                Win {
+                    card: 0,
+                    turn: 13,
+                    number: 16,
+                    pattern: Pattern::Row(2),
+                    score: 2192
+                },
+                Win {
+                    card: 1,
+                    turn: 14,
+                    number: 13,
+                    pattern: Pattern::Column(2),
+                    score: 1924
+                },
+            ]
+        );
+        assert_eq!(tournament.losers, vec![3]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn rules() -> Result<()> {
+        let (sequence, bingo_cards) = parse_input("1,2,3,4,5,6,7,8,9\n\n1 2 3\n4 5 6\n7 8 9")?;
+
+        let score_turn = |rules| -> Result<(Pattern, usize)> {
+            let tournament = play_tournament(&sequence, bingo_cards.clone(), rules)?;
+
+            Ok((tournament.wins[0].pattern, tournament.wins[0].turn))
+        };
+
+        assert_eq!(score_turn(Rules::default())?, (Pattern::Row(0), 2));
+        assert_eq!(
+            score_turn(Rules::Lines { diagonals: true })?,
+            (Pattern::Row(0), 2)
+        );
+        assert_eq!(score_turn(Rules::Corners)?, (Pattern::Corners, 8));
+        assert_eq!(score_turn(Rules::FullHouse)?, (Pattern::FullHouse, 8));
+
+        let (sequence, bingo_cards) = parse_input("3,5,9,7\n\n1 2 3\n4 5 6\n7 8 9")?;
+        let tournament = play_tournament(&sequence, bingo_cards, Rules::Lines { diagonals: true })?;
+        assert_eq!(tournament.wins[0].pattern, Pattern::AntiDiagonal);
+        assert_eq!(tournament.wins[0].turn, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn generated_cards() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(4);
+
+        for (rows, columns) in [(5, 5), (3, 7), (12, 12)] {
+            let input = generate_input(&mut rng, rows, columns, 20)?;
+            let (sequence, bingo_cards) = parse_input(trim_newlines(&input))?;
+
+            assert_eq!(bingo_cards.len(), 20);
+            assert!(
+                bingo_cards
+                    .iter()
+                    .all(|card| card.len() == rows && card[0].len() == columns)
+            );
+
+            for rules in ["lines", "diagonals", "full-house", "corners"] {
+                let tournament = play_tournament(&sequence, bingo_cards.clone(), rules.parse()?)?;
+
+                assert_eq!(tournament.wins.len(), 20);
+                assert!(tournament.losers.is_empty());
+            }
+        }
+
+        Ok(())
+    }
 }
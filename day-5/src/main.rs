@@ -1,60 +1,186 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+};
 
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-5.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-5.txt", &["overlaps", "heatmap"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
 fn part_1(input: &str) -> Result<usize> {
-    // Map of Coords against LineSegments crossing it.
-    let mut coords: HashMap<Coord, Vec<LineSegment>> = HashMap::new();
-
-    input
+    let line_segments = input
         .lines()
         .map(LineSegment::from_str)
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .filter(LineSegment::is_vertical_or_horizontal)
-        .for_each(|line_segment| {
-            for coord in line_segment.to_coords() {
-                coords.entry(coord).or_default().push(line_segment);
-            }
-        });
-
-    Ok(coords
-        .values()
-        .filter(|line_segments| line_segments.len() > 1)
-        .count())
+        .filter(|line_segment| {
+            line_segment
+                .as_ref()
+                .map_or(true, LineSegment::is_vertical_or_horizontal)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(count_overlaps(&line_segments))
 }
 
 fn part_2(input: &str) -> Result<usize> {
-    let mut coords: HashMap<Coord, Vec<LineSegment>> = HashMap::new();
+    let line_segments = input
+        .lines()
+        .map(LineSegment::from_str)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(count_overlaps(&line_segments))
+}
 
-    input
+fn run_extra(extra: &Extra) -> Result<String> {
+    let line_segments = extra
+        .input
         .lines()
         .map(LineSegment::from_str)
-        .collect::<Result<Vec<_>>>()?
-        .into_iter()
-        .for_each(|line_segment| {
-            for coord in line_segment.to_coords() {
-                coords.entry(coord).or_default().push(line_segment);
+        .collect::<Result<Vec<_>>>()?;
+
+    match (extra.name.as_str(), extra.args.as_slice()) {
+        ("overlaps", [stepping]) => match stepping.parse()? {
+            Stepping::Lattice => Ok(format!("{}\n", count_overlaps(&line_segments))),
+            Stepping::Bresenham => Ok(format!(
+                "{}\n",
+                count_overlaps_by_rasterizing(&line_segments, Stepping::Bresenham)
+            )),
+        },
+        ("heatmap", [stepping, format]) => {
+            let density = density(&line_segments, stepping.parse()?);
+
+            match format.as_str() {
+                "pgm" => Ok(heatmap_to_pgm(&density)),
+                "ppm" => Ok(heatmap_to_ppm(&density)),
+                _ => Err(anyhow!("Invalid format: {}", format)),
             }
-        });
+        }
+        ("overlaps", _) => Err(anyhow!("Usage: overlaps <lattice|bresenham>")),
+        _ => Err(anyhow!("Usage: heatmap <lattice|bresenham> <pgm|ppm>")),
+    }
+}
 
-    Ok(coords
-        .values()
-        .filter(|line_segments| line_segments.len() > 1)
-        .count())
+/// Counts the coords crossed by at least two line_segments, when stepping with
+/// [Stepping::Lattice].
+///
+/// Instead of visiting every coord, this works on whole stretches of each line, so the cost does
+/// not depend on the length of the line_segments.
+fn count_overlaps(line_segments: &[LineSegment]) -> usize {
+    // Group the line_segments by the line they lie on.
+    let mut lines: HashMap<Line, Vec<Stretch>> = HashMap::new();
+    for line_segment in line_segments {
+        let (line, start, end) = line_segment.to_run();
+        lines.entry(line).or_default().push((start, end));
+    }
+
+    // Stretches of each line crossed by at least one and at least two line_segments.
+    let mut covered = Vec::new();
+    let mut overlapped = HashMap::new();
+    let mut count = 0;
+
+    for (line, runs) in lines {
+        let (once, twice) = sweep(&runs, line.step_length());
+
+        count += twice
+            .iter()
+            .map(|(start, end)| ((end - start) / line.step_length() + 1) as usize)
+            .sum::<usize>();
+
+        covered.extend(once.into_iter().map(|(start, end)| (line, start, end)));
+        if !twice.is_empty() {
+            overlapped.insert(line, twice);
+        }
+    }
+
+    // Coords where different lines cross.
+    let mut crossings = HashSet::new();
+    for (index, &(line, start, end)) in covered.iter().enumerate() {
+        for &(other_line, other_start, other_end) in &covered[index + 1..] {
+            if let Some(coord) = line.crossing(&other_line)
+                && (start..=end).contains(&line.position(coord))
+                && (other_start..=other_end).contains(&other_line.position(coord))
+            {
+                crossings.insert(coord);
+            }
+        }
+    }
+
+    // A crossing may already be counted as part of an overlapped stretch, once per line.
+    let steps = overlapped
+        .keys()
+        .map(|line| line.step)
+        .collect::<HashSet<_>>();
+    let already_counted = crossings
+        .iter()
+        .map(|&coord| {
+            steps
+                .iter()
+                .filter(|&&step| {
+                    let line = Line::through(coord, step);
+
+                    overlapped
+                        .get(&line)
+                        .is_some_and(|twice| in_stretches(twice, line.position(coord)))
+                })
+                .count()
+        })
+        .sum::<usize>();
+
+    count + crossings.len() - already_counted
 }
 
-#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+/// Inclusive start and end [Line::position]s along a [Line].
+type Stretch = (i128, i128);
+
+/// Finds the stretches covered by at least one and at least two runs, all on the same [Line].
+fn sweep(runs: &[Stretch], step_length: i128) -> (Vec<Stretch>, Vec<Stretch>) {
+    // A run stops covering one step after its end.
+    let mut events = runs
+        .iter()
+        .flat_map(|&(start, end)| [(start, 1), (end + step_length, -1)])
+        .collect::<Vec<_>>();
+    events.sort_unstable();
+
+    let mut once = Vec::new();
+    let mut twice = Vec::new();
+    let mut coverage = 0;
+    let mut once_start = 0;
+    let mut twice_start = 0;
+
+    for (position, change) in events {
+        match (coverage, coverage + change) {
+            (0, 1) => once_start = position,
+            (1, 0) => once.push((once_start, position - step_length)),
+            (1, 2) => twice_start = position,
+            (2, 1) => twice.push((twice_start, position - step_length)),
+            _ => (),
+        }
+
+        coverage += change;
+    }
+
+    (once, twice)
+}
+
+/// Checks if position falls within any of the ascending, non-overlapping stretches.
+fn in_stretches(stretches: &[Stretch], position: i128) -> bool {
+    let index = stretches.partition_point(|&(start, _)| start <= position);
+
+    index > 0 && stretches[index - 1].1 >= position
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 struct Coord {
     x: u32,
     y: u32,
@@ -101,34 +227,271 @@ impl LineSegment {
         self.start.x == self.end.x || self.start.y == self.end.y
     }
 
-    fn to_coords(self) -> Vec<Coord> {
-        // Assumption: self.start != self.end.
-        // Assumption: Line must be vertical, horizontal or perfectly 45-degree diagonal.
+    /// Gets the [Line] through self, and the [Line::position]s of its start and end, in order.
+    fn to_run(self) -> (Line, i128, i128) {
+        let dx = i64::from(self.end.x) - i64::from(self.start.x);
+        let dy = i64::from(self.end.y) - i64::from(self.start.y);
+
+        // Smallest step between coords on the line, pointing right or down.
+        let divisor = gcd(dx.abs(), dy.abs());
+        let step = match (dx, dy) {
+            (0, 0) => (1, 0),
+            (dx, dy) if dx < 0 || (dx == 0 && dy < 0) => (-dx / divisor, -dy / divisor),
+            (dx, dy) => (dx / divisor, dy / divisor),
+        };
+
+        let line = Line::through(self.start, step);
+        let start = line.position(self.start);
+        let end = line.position(self.end);
 
-        let mut coords = Vec::new();
+        (line, start.min(end), start.max(end))
+    }
 
-        let x_range: Box<dyn Iterator<Item = _>> = match (self.start.x, self.end.x) {
-            (start, end) if start == end => Box::new((start..=start).cycle()), // vertical line
-            (start, end) if start < end => Box::new(start..=end),
-            (start, end) => Box::new((end..=start).rev()),
-        };
-        let y_range: Box<dyn Iterator<Item = _>> = match (self.start.y, self.end.y) {
-            (start, end) if start == end => Box::new((start..=start).cycle()), // horizontal line
-            (start, end) if start < end => Box::new(start..=end),
-            (start, end) => Box::new((end..=start).rev()),
+    /// Gets the coords crossed by self, from start to end.
+    fn to_coords(self, stepping: Stepping) -> Vec<Coord> {
+        let (x0, y0) = (i64::from(self.start.x), i64::from(self.start.y));
+        let (x1, y1) = (i64::from(self.end.x), i64::from(self.end.y));
+        let to_coord = |x: i64, y: i64| Coord {
+            x: x as u32,
+            y: y as u32,
         };
 
-        for (x, y) in x_range.zip(y_range) {
-            coords.push(Coord { x, y });
+        match stepping {
+            Stepping::Lattice => {
+                let divisor = gcd((x1 - x0).abs(), (y1 - y0).abs());
+                if divisor == 0 {
+                    return vec![self.start];
+                }
+
+                let (dx, dy) = ((x1 - x0) / divisor, (y1 - y0) / divisor);
+
+                (0..=divisor)
+                    .map(|step| to_coord(x0 + step * dx, y0 + step * dy))
+                    .collect()
+            }
+            Stepping::Bresenham => {
+                let (dx, sx) = ((x1 - x0).abs(), (x1 - x0).signum());
+                let (dy, sy) = (-(y1 - y0).abs(), (y1 - y0).signum());
+
+                let mut coords = Vec::new();
+                let (mut x, mut y) = (x0, y0);
+                let mut error = dx + dy;
+
+                loop {
+                    coords.push(to_coord(x, y));
+
+                    if x == x1 && y == y1 {
+                        break;
+                    }
+
+                    let doubled_error = 2 * error;
+                    if doubled_error >= dy {
+                        error += dy;
+                        x += sx;
+                    }
+                    if doubled_error <= dx {
+                        error += dx;
+                        y += sy;
+                    }
+                }
+
+                coords
+            }
+        }
+    }
+}
+
+/// How a [LineSegment] at any angle is turned into coords.
+#[derive(Clone, Copy)]
+enum Stepping {
+    /// Only the coords lying exactly on the line.
+    Lattice,
+
+    /// The closest coord in every column or row, whichever is longer.
+    Bresenham,
+}
+
+impl FromStr for Stepping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "lattice" => Ok(Stepping::Lattice),
+            "bresenham" => Ok(Stepping::Bresenham),
+            _ => Err(anyhow!("Invalid stepping: {}", s)),
+        }
+    }
+}
+
+/// An infinite line through coords that are step apart.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
+struct Line {
+    /// Smallest step between coords on the line, pointing right or down.
+    step: (i64, i64),
+
+    /// step.1 * x - step.0 * y, which is the same for every point on the line.
+    ///
+    /// Products of a step and a u32 coord can exceed i64, so they are all taken in i128.
+    constant: i128,
+}
+
+impl Line {
+    fn through(coord: Coord, step: (i64, i64)) -> Self {
+        Line {
+            step,
+            constant: i128::from(step.1) * i128::from(coord.x)
+                - i128::from(step.0) * i128::from(coord.y),
+        }
+    }
+
+    /// Gets the position of coord along the line, assuming it lies on the line.
+    ///
+    /// Positions increase by [Line::step_length] from one coord to the next.
+    fn position(&self, coord: Coord) -> i128 {
+        i128::from(self.step.0) * i128::from(coord.x)
+            + i128::from(self.step.1) * i128::from(coord.y)
+    }
+
+    fn step_length(&self) -> i128 {
+        let (dx, dy) = (i128::from(self.step.0), i128::from(self.step.1));
+
+        dx * dx + dy * dy
+    }
+
+    /// Finds the coord where self and other cross, if any.
+    fn crossing(&self, other: &Line) -> Option<Coord> {
+        let (dx, dy) = (i128::from(self.step.0), i128::from(self.step.1));
+        let (other_dx, other_dy) = (i128::from(other.step.0), i128::from(other.step.1));
+
+        let determinant = dx * other_dy - other_dx * dy;
+        if determinant == 0 {
+            return None;
+        }
+
+        let x = other.constant * dx - self.constant * other_dx;
+        let y = other.constant * dy - self.constant * other_dy;
+        if x % determinant != 0 || y % determinant != 0 {
+            return None;
         }
 
-        coords
+        Some(Coord {
+            x: u32::try_from(x / determinant).ok()?,
+            y: u32::try_from(y / determinant).ok()?,
+        })
     }
 }
 
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+/// Counts overlaps by visiting every coord of every line segment.
+fn count_overlaps_by_rasterizing(line_segments: &[LineSegment], stepping: Stepping) -> usize {
+    density(line_segments, stepping)
+        .values()
+        .filter(|&&count| count > 1)
+        .count()
+}
+
+/// Maps each coord against the number of line_segments crossing it.
+fn density(line_segments: &[LineSegment], stepping: Stepping) -> HashMap<Coord, usize> {
+    let mut coords = HashMap::new();
+
+    for line_segment in line_segments {
+        for coord in line_segment.to_coords(stepping) {
+            *coords.entry(coord).or_default() += 1;
+        }
+    }
+
+    coords
+}
+
+/// Gets the top left and bottom right corners of all coords in density.
+fn bounds(density: &HashMap<Coord, usize>) -> (Coord, Coord) {
+    let x = density.keys().map(|coord| coord.x);
+    let y = density.keys().map(|coord| coord.y);
+
+    (
+        Coord {
+            x: x.clone().min().unwrap_or_default(),
+            y: y.clone().min().unwrap_or_default(),
+        },
+        Coord {
+            x: x.max().unwrap_or_default(),
+            y: y.max().unwrap_or_default(),
+        },
+    )
+}
+
+/// Renders density as a plain (ASCII) PGM image, cropped to the [bounds] of the coords.
+///
+/// Each pixel value is the number of line segments crossing it.
+fn heatmap_to_pgm(density: &HashMap<Coord, usize>) -> String {
+    let (top_left, bottom_right) = bounds(density);
+    let max = density.values().copied().max().unwrap_or_default().max(1);
+
+    let mut pgm = format!(
+        "P2\n{} {}\n{}\n",
+        bottom_right.x - top_left.x + 1,
+        bottom_right.y - top_left.y + 1,
+        max
+    );
+
+    for y in top_left.y..=bottom_right.y {
+        let row = (top_left.x..=bottom_right.x)
+            .map(|x| {
+                density
+                    .get(&Coord { x, y })
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string()
+            })
+            .collect::<Vec<_>>();
+
+        pgm += &row.join(" ");
+        pgm += "\n";
+    }
+
+    pgm
+}
+
+/// Renders density as a plain (ASCII) PPM image, cropped to the [bounds] of the coords.
+///
+/// Pixels go from black through red and yellow to white as more line segments cross them.
+fn heatmap_to_ppm(density: &HashMap<Coord, usize>) -> String {
+    let (top_left, bottom_right) = bounds(density);
+    let max = density.values().copied().max().unwrap_or_default().max(1);
+
+    let mut ppm = format!(
+        "P3\n{} {}\n255\n",
+        bottom_right.x - top_left.x + 1,
+        bottom_right.y - top_left.y + 1,
+    );
+
+    for y in top_left.y..=bottom_right.y {
+        let row = (top_left.x..=bottom_right.x)
+            .map(|x| {
+                let count = density.get(&Coord { x, y }).copied().unwrap_or_default();
+
+                // Each channel ramps up over its own third of the range.
+                let channel =
+                    |third: usize| (count * 3).saturating_sub(third * max).min(max) * 255 / max;
+
+                format!("{} {} {}", channel(0), channel(1), channel(2))
+            })
+            .collect::<Vec<_>>();
+
+        ppm += &row.join(" ");
+        ppm += "\n";
+    }
+
+    ppm
+}
+
 #[cfg(test)]
 mod tests {
     use cli::trim_newlines;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
 
     use super::*;
 
@@ -158,4 +521,142 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn matches_rasterizing() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut next = |bound: u32| rng.random_range(0..bound);
+
+        for _ in 0..20 {
+            let line_segments = (0..100)
+                .map(|_| {
+                    let start = Coord {
+                        x: next(50) + 20,
+                        y: next(50) + 20,
+                    };
+                    let length = next(20) + 1;
+                    let end = match next(4) {
+                        0 => Coord {
+                            x: start.x + length,
+                            ..start
+                        },
+                        1 => Coord {
+                            y: start.y - length,
+                            ..start
+                        },
+                        2 => Coord {
+                            x: start.x - length,
+                            y: start.y - length,
+                        },
+                        _ => Coord {
+                            x: start.x + length,
+                            y: start.y - length,
+                        },
+                    };
+
+                    LineSegment { start, end }
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                count_overlaps(&line_segments),
+                count_overlaps_by_rasterizing(&line_segments, Stepping::Lattice)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn long_lines() -> Result<()> {
+        let input = r"
+0,0 -> 1000000,0
+1500000,0 -> 500000,0
+0,1000000 -> 0,0
+0,1000000 -> 1000000,0
+";
+
+        assert_eq!(part_2(trim_newlines(input))?, 500003);
+
+        // Lines at odd angles across big coords, which overflow i64 part way through.
+        let input = r"
+0,0 -> 3000000,2999999
+0,2999999 -> 3000000,0
+0,0 -> 3000000,2999998
+0,2999998 -> 3000000,0
+4000000000,1 -> 0,1
+4000000000,0 -> 0,2
+";
+
+        assert_eq!(part_2(trim_newlines(input))?, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn any_angle() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut next = |bound: u32| rng.random_range(0..bound);
+
+        for _ in 0..20 {
+            let line_segments = (0..100)
+                .map(|_| LineSegment {
+                    start: Coord {
+                        x: next(30),
+                        y: next(30),
+                    },
+                    end: Coord {
+                        x: next(30),
+                        y: next(30),
+                    },
+                })
+                .collect::<Vec<_>>();
+
+            assert_eq!(
+                count_overlaps(&line_segments),
+                count_overlaps_by_rasterizing(&line_segments, Stepping::Lattice)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn stepping() -> Result<()> {
+        let line_segment = LineSegment::from_str("0,0 -> 4,2")?;
+
+        assert_eq!(
+            line_segment.to_coords(Stepping::Lattice),
+            vec![
+                Coord { x: 0, y: 0 },
+                Coord { x: 2, y: 1 },
+                Coord { x: 4, y: 2 }
+            ]
+        );
+        assert_eq!(line_segment.to_coords(Stepping::Bresenham).len(), 5);
+
+        let point = LineSegment::from_str("2,1 -> 2,1")?;
+        assert_eq!(point.to_coords(Stepping::Lattice), vec![point.start]);
+        assert_eq!(point.to_coords(Stepping::Bresenham), vec![point.start]);
+        assert_eq!(count_overlaps(&[line_segment, point]), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn heatmap() -> Result<()> {
+        let line_segments = [
+            LineSegment::from_str("1,1 -> 3,1")?,
+            LineSegment::from_str("2,0 -> 2,1")?,
+        ];
+        let density = density(&line_segments, Stepping::Lattice);
+
+        assert_eq!(heatmap_to_pgm(&density), "P2\n3 2\n2\n0 1 0\n1 2 1\n");
+        assert_eq!(
+            heatmap_to_ppm(&density),
+            "P3\n3 2\n255\n0 0 0 255 127 0 0 0 0\n255 127 0 255 255 255 255 127 0\n"
+        );
+
+        Ok(())
+    }
 }
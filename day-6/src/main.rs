@@ -1,74 +1,261 @@
-use std::collections::HashMap;
+use std::ops::{Add, Mul};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use num_bigint::BigUint;
+use num_traits::{One, Zero};
 
-use cli::{Part, get_part};
+use cli::{
+    Command, Extra, Part, get_command,
+    matrix::{Matrix, matrix_power},
+};
 
 fn main() {
-    match get_part("inputs/day-6.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-6.txt", &["population", "series"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
-fn part_1(input: &str) -> Result<usize> {
-    let mut fishes = input
-        .split_terminator(",")
-        .map(str::parse::<u8>)
-        .collect::<Result<Vec<_>, _>>()?;
+fn part_1(input: &str) -> Result<u64> {
+    let life_cycle = LifeCycle::default();
 
-    for _ in 0..80 {
-        let fish_to_add = fishes.iter().filter(|&fish| *fish == 0).count();
+    Ok(u64::try_from(population(
+        &parse_input(input, life_cycle)?,
+        life_cycle,
+        80,
+    ))?)
+}
+
+fn part_2(input: &str) -> Result<u64> {
+    let life_cycle = LifeCycle::default();
+
+    Ok(u64::try_from(population(
+        &parse_input(input, life_cycle)?,
+        life_cycle,
+        256,
+    ))?)
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    let Some((days, options)) = extra.args.split_first() else {
+        return Err(anyhow!(
+            "Usage: {} <DAYS> [interval=<DAYS>] [delay=<DAYS>] [modulus=<MODULUS>]",
+            extra.name
+        ));
+    };
+    let days = days.parse()?;
+
+    let mut life_cycle = LifeCycle::default();
+    let mut modulus = None;
+    for option in options {
+        match option.split_once("=") {
+            Some(("interval", value)) => life_cycle.spawn_interval = value.parse()?,
+            Some(("delay", value)) => life_cycle.newborn_delay = value.parse()?,
+            Some(("modulus", value)) if extra.name == "population" => {
+                modulus = Some(value.parse()?)
+            }
+            _ => return Err(anyhow!("Invalid option: {}", option)),
+        }
+    }
+    if !(1..=MAX_TIMER + 1).contains(&life_cycle.spawn_interval) {
+        return Err(anyhow!(
+            "Invalid spawn interval: {}",
+            life_cycle.spawn_interval
+        ));
+    }
+    if life_cycle.newborn_delay > MAX_TIMER + 1 - life_cycle.spawn_interval {
+        return Err(anyhow!(
+            "Invalid newborn delay: {}",
+            life_cycle.newborn_delay
+        ));
+    }
+
+    let fishes = parse_input(&extra.input, life_cycle)?;
+
+    match (extra.name.as_str(), modulus) {
+        ("population", None) => Ok(format!("{}\n", population(&fishes, life_cycle, days))),
+        ("population", Some(modulus)) => Ok(format!(
+            "{}\n",
+            population_modulo(&fishes, life_cycle, days, modulus)?
+        )),
+        _ => Ok(time_series_to_csv(&fishes, life_cycle, days)),
+    }
+}
+
+/// Highest timer value of any fish.
+///
+/// There is a count, and a row and a column of the transition matrix, for every timer value, so
+/// the cost grows quickly with this.
+const MAX_TIMER: usize = 100;
 
-        fishes = fishes
-            .into_iter()
-            .map(|fish| if fish == 0 { 6 } else { fish - 1 })
-            .collect();
+/// How lanternfishes age and spawn.
+#[derive(Clone, Copy)]
+struct LifeCycle {
+    /// Days between spawns of a fish.
+    spawn_interval: usize,
 
-        fishes.extend(vec![8; fish_to_add]);
+    /// Extra days before a new fish spawns for the first time.
+    newborn_delay: usize,
+}
+
+impl Default for LifeCycle {
+    fn default() -> Self {
+        LifeCycle {
+            spawn_interval: 7,
+            newborn_delay: 2,
+        }
+    }
+}
+
+impl LifeCycle {
+    /// Timer of a fish right after it spawns.
+    fn reset_timer(&self) -> usize {
+        self.spawn_interval - 1
     }
 
-    Ok(fishes.len())
+    /// Timer of a new fish.
+    fn newborn_timer(&self) -> usize {
+        self.reset_timer() + self.newborn_delay
+    }
 }
 
-fn part_2(input: &str) -> Result<usize> {
-    let fishes = input
-        .split_terminator(",")
-        .map(str::parse::<u8>)
-        .collect::<Result<Vec<_>, _>>()?;
+/// Counts the fishes with each timer value.
+///
+/// There is a count for every timer value up to the highest of the new fish timer and the timers
+/// in input.
+fn parse_input(input: &str, life_cycle: LifeCycle) -> Result<Vec<u64>> {
+    let mut fishes = vec![0; life_cycle.newborn_timer() + 1];
+
+    for fish in input.split_terminator(",").map(str::parse::<usize>) {
+        let fish = fish?;
+        if fish > MAX_TIMER {
+            return Err(anyhow!("Invalid timer: {}", fish));
+        }
+
+        if fish >= fishes.len() {
+            fishes.resize(fish + 1, 0);
+        }
 
-    let mut cache = HashMap::new();
-    let fish_counts = (1..=5) // fish runs from 1 to 5 inclusive
-        .map(|fish_timer| self_plus_progeny(fish_timer, 256, &mut cache))
+        fishes[fish] += 1;
+    }
+
+    Ok(fishes)
+}
+
+/// Counts the fishes after days, exactly.
+///
+/// The count grows exponentially, e.g. by roughly 10% a day for the default [LifeCycle], so the
+/// result has about days / 25 digits.
+fn population(fishes: &[u64], life_cycle: LifeCycle, days: u64) -> BigUint {
+    let fishes = fishes
+        .iter()
+        .copied()
+        .map(BigUint::from)
         .collect::<Vec<_>>();
+    let transition = transition_matrix(fishes.len(), life_cycle, BigUint::zero(), BigUint::one());
 
-    Ok(fishes
-        .into_iter()
-        .map(|fish| fish_counts[fish as usize - 1])
-        .sum())
+    count_after(&fishes, transition, days, &|value| value)
 }
 
-fn self_plus_progeny(
-    fish_timer: u8,
-    days_left: u16,
-    cache: &mut HashMap<(u8, u16), usize>,
-) -> usize {
-    if cache.contains_key(&(fish_timer, days_left)) {
-        return cache[&(fish_timer, days_left)];
+/// Counts the fishes after days, modulo modulus.
+///
+/// The matrix has a row per timer value and is squared once per bit of days, so even 10^12
+/// days take a moment.
+fn population_modulo(
+    fishes: &[u64],
+    life_cycle: LifeCycle,
+    days: u64,
+    modulus: u64,
+) -> Result<u64> {
+    if modulus == 0 {
+        return Err(anyhow!("Invalid modulus: {}", modulus));
     }
 
-    if fish_timer as u16 >= days_left {
-        return 1;
+    let modulus = u128::from(modulus);
+    let fishes = fishes
+        .iter()
+        .map(|&count| u128::from(count) % modulus)
+        .collect::<Vec<_>>();
+    let transition = transition_matrix(fishes.len(), life_cycle, 0, 1 % modulus);
+
+    Ok(count_after(&fishes, transition, days, &|value| value % modulus) as u64)
+}
+
+/// Renders the fish counts on each day as CSV with a header row: the day, the total, then the
+/// count for each timer value.
+fn time_series_to_csv(fishes: &[u64], life_cycle: LifeCycle, days: u64) -> String {
+    let mut fishes = fishes
+        .iter()
+        .copied()
+        .map(BigUint::from)
+        .collect::<Vec<_>>();
+
+    let mut csv = String::from("day,total");
+    for timer in 0..fishes.len() {
+        csv += &format!(",timer_{timer}");
     }
+    csv += "\n";
 
-    let count = if fish_timer == 0 {
-        self_plus_progeny(6, days_left - 1, cache) + self_plus_progeny(8, days_left - 1, cache)
-    } else {
-        self_plus_progeny(fish_timer - 1, days_left - 1, cache)
-    };
+    for day in 0..=days {
+        csv += &format!("{},{}", day, fishes.iter().sum::<BigUint>());
+        for count in &fishes {
+            csv += &format!(",{count}");
+        }
+        csv += "\n";
+
+        // Every timer counts down by one, and those at 0 reset and spawn.
+        let spawning = fishes.remove(0);
+        fishes.push(BigUint::zero());
+        fishes[life_cycle.reset_timer()] += &spawning;
+        fishes[life_cycle.newborn_timer()] += spawning;
+    }
 
-    *cache.entry((fish_timer, days_left)).or_insert(count)
+    csv
+}
+
+/// Builds the matrix that maps the fish counts by timer on one day to those on the next day.
+///
+/// size is the number of timer values, which must exceed [LifeCycle::newborn_timer].
+fn transition_matrix<T: Clone + Add<Output = T>>(
+    size: usize,
+    life_cycle: LifeCycle,
+    zero: T,
+    one: T,
+) -> Matrix<T> {
+    let mut matrix = vec![vec![zero; size]; size];
+
+    // Every timer counts down by one...
+    for timer in 1..size {
+        matrix[timer - 1][timer] = one.clone();
+    }
+
+    // ...except 0, which resets and spawns a new fish, possibly with the same timer.
+    matrix[life_cycle.reset_timer()][0] = one.clone();
+    let newborn = &mut matrix[life_cycle.newborn_timer()][0];
+    *newborn = newborn.clone() + one;
+
+    matrix
+}
+
+/// Applies transition to fishes days times, using reduce to keep values in range, and returns the
+/// total count.
+fn count_after<T>(fishes: &[T], transition: Matrix<T>, days: u64, reduce: &impl Fn(T) -> T) -> T
+where
+    T: Clone + Zero + One + Add<Output = T> + Mul<Output = T>,
+{
+    let transition = matrix_power(transition, days, reduce);
+
+    transition
+        .into_iter()
+        .flat_map(|row| row.into_iter().zip(fishes.iter().cloned()))
+        .fold(T::zero(), |acc, (factor, count)| {
+            reduce(acc + factor * count)
+        })
 }
 
 #[cfg(test)]
@@ -92,4 +279,44 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn modulo() -> Result<()> {
+        let life_cycle = LifeCycle::default();
+        let fishes = parse_input(EXAMPLE_INPUT, life_cycle)?;
+        let prime = 1_000_000_007;
+
+        assert_eq!(
+            BigUint::from(population_modulo(&fishes, life_cycle, 1000, prime)?),
+            population(&fishes, life_cycle, 1000) % prime
+        );
+        assert_eq!(population_modulo(&fishes, life_cycle, 18, prime)?, 26);
+        assert!(population_modulo(&fishes, life_cycle, 1_000_000_000_000, prime)? < prime);
+
+        Ok(())
+    }
+
+    #[test]
+    fn custom_life_cycle() -> Result<()> {
+        let life_cycle = LifeCycle {
+            spawn_interval: 3,
+            newborn_delay: 0,
+        };
+        let fishes = parse_input("0,5", life_cycle)?;
+
+        assert_eq!(fishes, vec![1, 0, 0, 0, 0, 1]);
+        assert_eq!(
+            time_series_to_csv(&fishes, life_cycle, 4),
+            "day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5\n\
+             0,2,1,0,0,0,0,1\n\
+             1,3,0,0,2,0,1,0\n\
+             2,3,0,2,0,1,0,0\n\
+             3,3,2,0,1,0,0,0\n\
+             4,5,0,1,4,0,0,0\n"
+        );
+        assert_eq!(population(&fishes, life_cycle, 4), BigUint::from(5_u8));
+        assert!(parse_input("0,1000000000000", life_cycle).is_err());
+
+        Ok(())
+    }
 }
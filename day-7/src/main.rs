@@ -1,65 +1,318 @@
+use std::{cmp::Ordering, ops::RangeInclusive, str::FromStr};
+
 use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-7.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-7.txt", &["align", "curve"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
 
-fn part_1(input: &str) -> Result<u32> {
-    let crabs = input
-        .split_terminator(",")
-        .map(str::parse::<u32>)
-        .collect::<Result<Vec<_>, _>>()?;
+fn part_1(input: &str) -> Result<u64> {
+    Ok(align(&parse_input(input)?, &Linear)?.fuel)
+}
 
-    let Some(&min_pos) = crabs.iter().min() else {
-        return Err(anyhow!("Cannot determine min pos"));
-    };
-    let Some(&max_pos) = crabs.iter().max() else {
-        return Err(anyhow!("Cannot determine max pos"));
+fn part_2(input: &str) -> Result<u64> {
+    Ok(align(&parse_input(input)?, &Triangular)?.fuel)
+}
+
+fn run_extra(extra: &Extra) -> Result<String> {
+    let [cost] = extra.args.as_slice() else {
+        return Err(anyhow!(
+            "Usage: {} <linear|triangular|quadratic|capped=<FUEL>|asymmetric=<UPHILL>,<DOWNHILL>>",
+            extra.name
+        ));
     };
+    let cost = parse_cost(cost)?;
+    let crabs = parse_input(&extra.input)?;
 
-    (min_pos..=max_pos)
-        .map(|pos| crabs.iter().map(|crab| crab.abs_diff(pos)).sum())
-        .min()
-        .ok_or(anyhow!("Cannot determine min fuel"))
+    if extra.name == "curve" {
+        return fuel_curve_to_csv(&crabs, cost.as_ref());
+    }
+
+    let alignment = align(&crabs, cost.as_ref())?;
+
+    Ok(format!(
+        "position {}, fuel {}\n",
+        alignment.position, alignment.fuel
+    ))
 }
 
-fn part_2(input: &str) -> Result<u32> {
+/// Parses crabs separated by commas. Each is either a position, or "<POSITION> x <COUNT>" for
+/// several crabs at the same position.
+fn parse_input(input: &str) -> Result<Vec<Crab>> {
     let crabs = input
         .split_terminator(",")
-        .map(str::parse::<u32>)
-        .collect::<Result<Vec<_>, _>>()?;
+        .map(Crab::from_str)
+        .collect::<Result<Vec<_>>>()?;
+
+    if crabs.is_empty() {
+        return Err(anyhow!("No crabs"));
+    }
+
+    Ok(crabs)
+}
+
+fn parse_cost(cost: &str) -> Result<Box<dyn FuelCost>> {
+    match cost.split_once("=") {
+        None if cost == "linear" => Ok(Box::new(Linear)),
+        None if cost == "triangular" => Ok(Box::new(Triangular)),
+        None if cost == "quadratic" => Ok(Box::new(Quadratic)),
+        Some(("capped", cap)) => Ok(Box::new(Capped {
+            cost: Linear,
+            cap: cap.parse()?,
+        })),
+        Some(("asymmetric", rates)) => {
+            let Some((uphill, downhill)) = rates.split_once(",") else {
+                return Err(anyhow!("Cannot split rates: {}", rates));
+            };
+
+            Ok(Box::new(Asymmetric {
+                uphill: uphill.parse()?,
+                downhill: downhill.parse()?,
+            }))
+        }
+        _ => Err(anyhow!("Invalid cost: {}", cost)),
+    }
+}
+
+/// One or more crabs at the same position.
+#[derive(Clone, Copy)]
+struct Crab {
+    position: u32,
+    count: u64,
+}
+
+impl FromStr for Crab {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let (position, count) = match s.split_once("x") {
+            Some((position, count)) => (position.trim().parse()?, count.trim().parse()?),
+            None => (s.trim().parse()?, 1),
+        };
+
+        Ok(Crab { position, count })
+    }
+}
+
+/// Fuel needed by a single crab to move between positions.
+trait FuelCost {
+    fn fuel(&self, from: u32, to: u32) -> u64;
+
+    /// Whether the total fuel, as the target position moves from left to right, never goes up
+    /// and then down again. [align] searches instead of trying every position if so.
+    ///
+    /// Costs must opt in, as a wrong answer here makes [align] miss the optimum.
+    fn is_convex(&self) -> bool {
+        false
+    }
+
+    /// Narrows down the positions where the least total fuel can be, if known in advance.
+    fn candidates(&self, _crabs: &[Crab]) -> Option<RangeInclusive<u32>> {
+        None
+    }
+}
+
+/// One fuel per move.
+struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, from: u32, to: u32) -> u64 {
+        u64::from(from.abs_diff(to))
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    /// The (weighted) median is optimal.
+    fn candidates(&self, crabs: &[Crab]) -> Option<RangeInclusive<u32>> {
+        let mut crabs = crabs.to_vec();
+        crabs.sort_unstable_by_key(|crab| crab.position);
+
+        let total = crabs.iter().map(|crab| crab.count).sum::<u64>();
+        let mut count = 0;
+        let median = crabs.into_iter().find(|crab| {
+            count += crab.count;
+            count * 2 >= total
+        })?;
+
+        Some(median.position..=median.position)
+    }
+}
+
+/// One more fuel for each move than the previous one.
+struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, from: u32, to: u32) -> u64 {
+        let moves = u64::from(from.abs_diff(to));
+
+        moves * (moves + 1) / 2 // sum of N series
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+
+    /// The optimum lies within one position of the (weighted) mean.
+    fn candidates(&self, crabs: &[Crab]) -> Option<RangeInclusive<u32>> {
+        let total = crabs.iter().map(|crab| crab.count).sum::<u64>();
+        let sum = crabs
+            .iter()
+            .map(|crab| u64::from(crab.position) * crab.count)
+            .sum::<u64>();
+        let mean = u32::try_from(sum.checked_div(total)?).ok()?;
+
+        Some(mean.saturating_sub(1)..=mean.saturating_add(2))
+    }
+}
+
+/// Square of the number of moves.
+struct Quadratic;
 
-    let Some(&min_pos) = crabs.iter().min() else {
+impl FuelCost for Quadratic {
+    fn fuel(&self, from: u32, to: u32) -> u64 {
+        u64::from(from.abs_diff(to)).pow(2)
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// Another [FuelCost], but never more than cap.
+struct Capped<C: FuelCost> {
+    cost: C,
+    cap: u64,
+}
+
+impl<C: FuelCost> FuelCost for Capped<C> {
+    fn fuel(&self, from: u32, to: u32) -> u64 {
+        self.cost.fuel(from, to).min(self.cap)
+    }
+}
+
+/// A fixed fuel per move, depending on whether the move goes uphill (to a higher position) or
+/// downhill.
+struct Asymmetric {
+    uphill: u64,
+    downhill: u64,
+}
+
+impl FuelCost for Asymmetric {
+    fn fuel(&self, from: u32, to: u32) -> u64 {
+        let moves = u64::from(from.abs_diff(to));
+
+        if to > from {
+            moves * self.uphill
+        } else {
+            moves * self.downhill
+        }
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// A position to align to, and the fuel needed to get every crab there.
+#[derive(Debug, Eq, PartialEq)]
+struct Alignment {
+    position: u32,
+    fuel: u64,
+}
+
+fn total_fuel(crabs: &[Crab], position: u32, cost: &dyn FuelCost) -> u64 {
+    crabs
+        .iter()
+        .map(|crab| crab.count * cost.fuel(crab.position, position))
+        .sum()
+}
+
+/// Gets the positions between the outermost crabs.
+fn span(crabs: &[Crab]) -> Result<RangeInclusive<u32>> {
+    let Some(min_pos) = crabs.iter().map(|crab| crab.position).min() else {
         return Err(anyhow!("Cannot determine min pos"));
     };
-    let Some(&max_pos) = crabs.iter().max() else {
+    let Some(max_pos) = crabs.iter().map(|crab| crab.position).max() else {
         return Err(anyhow!("Cannot determine max pos"));
     };
 
-    (min_pos..=max_pos)
-        .map(|pos| {
-            crabs
-                .iter()
-                .map(|crab| {
-                    let moves = crab.abs_diff(pos);
+    Ok(min_pos..=max_pos)
+}
+
+/// Finds the [Alignment] with the least fuel.
+///
+/// Only the [FuelCost::candidates] are checked, if any. A convex cost is narrowed down with a
+/// ternary search, while any other cost tries every position.
+fn align(crabs: &[Crab], cost: &dyn FuelCost) -> Result<Alignment> {
+    let span = span(crabs)?;
+    let candidates = cost.candidates(crabs).unwrap_or(span.clone());
 
-                    moves * (moves + 1) / 2 // sum of N series
-                })
-                .sum()
+    let mut low = *candidates.start().max(span.start());
+    let mut high = *candidates.end().min(span.end());
+
+    // Narrow down until only a few candidates are left. With a convex cost, the total fuel cannot
+    // go down and then up again, so the side with more fuel can be discarded.
+    while cost.is_convex() && high - low > 2 {
+        let left = low + (high - low) / 3;
+        let right = high - (high - low) / 3;
+
+        match total_fuel(crabs, left, cost).cmp(&total_fuel(crabs, right, cost)) {
+            Ordering::Less => high = right - 1,
+            Ordering::Greater => low = left + 1,
+            Ordering::Equal => (low, high) = (left, right),
+        }
+    }
+
+    (low..=high)
+        .map(|position| Alignment {
+            position,
+            fuel: total_fuel(crabs, position, cost),
         })
-        .min()
+        .min_by_key(|alignment| alignment.fuel)
         .ok_or(anyhow!("Cannot determine min fuel"))
 }
 
+/// Finds the [Alignment] with the least fuel by trying every position between the outermost
+/// crabs.
+#[cfg(test)]
+fn align_by_brute_force(crabs: &[Crab], cost: &dyn FuelCost) -> Result<Alignment> {
+    span(crabs)?
+        .map(|position| Alignment {
+            position,
+            fuel: total_fuel(crabs, position, cost),
+        })
+        .min_by_key(|alignment| alignment.fuel)
+        .ok_or(anyhow!("Cannot determine min fuel"))
+}
+
+/// Renders the total fuel for every position between the outermost crabs as CSV with a header
+/// row.
+fn fuel_curve_to_csv(crabs: &[Crab], cost: &dyn FuelCost) -> Result<String> {
+    let mut csv = String::from("position,fuel\n");
+
+    for position in span(crabs)? {
+        csv += &format!("{},{}\n", position, total_fuel(crabs, position, cost));
+    }
+
+    Ok(csv)
+}
+
 #[cfg(test)]
 mod tests {
     use cli::trim_newlines;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
 
     use super::*;
 
@@ -78,4 +331,70 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn matches_brute_force() -> Result<()> {
+        assert_eq!(
+            align(&parse_input(EXAMPLE_INPUT)?, &Triangular)?,
+            Alignment {
+                position: 5,
+                fuel: 168
+            }
+        );
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut next = |bound: u32| rng.random_range(0..bound);
+
+        for count in 1..50 {
+            let spread = next(2000) + 1;
+            let crabs = (0..count)
+                .map(|_| Crab {
+                    position: next(spread),
+                    count: u64::from(next(3) + 1),
+                })
+                .collect::<Vec<_>>();
+
+            let costs: [&dyn FuelCost; 5] = [
+                &Linear,
+                &Triangular,
+                &Quadratic,
+                &Capped {
+                    cost: Triangular,
+                    cap: 5000,
+                },
+                &Asymmetric {
+                    uphill: 3,
+                    downhill: 1,
+                },
+            ];
+
+            for cost in costs {
+                assert_eq!(
+                    align(&crabs, cost)?.fuel,
+                    align_by_brute_force(&crabs, cost)?.fuel
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn weighted_crabs() -> Result<()> {
+        let crabs = parse_input("0 x 3,4,10 x 3")?;
+
+        assert_eq!(
+            align(&crabs, &Linear)?,
+            Alignment {
+                position: 4,
+                fuel: 30
+            }
+        );
+        assert_eq!(
+            fuel_curve_to_csv(&parse_input("1,3")?, &Quadratic)?,
+            "position,fuel\n1,4\n2,2\n3,4\n"
+        );
+
+        Ok(())
+    }
 }
@@ -1,17 +1,22 @@
 use std::{
-    collections::HashMap,
-    ops::{Add, Deref, Sub},
+    collections::{BTreeSet, HashSet},
+    fs,
     str::FromStr,
 };
 
 use anyhow::{Result, anyhow};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-8.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-8.txt", &["decode", "encode"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -41,81 +46,393 @@ fn part_1(input: &str) -> Result<usize> {
 }
 
 fn part_2(input: &str) -> Result<u32> {
-    split_input_into_signal_and_output(input)?
-        .into_iter()
-        .map(|(signal, output)| {
-            let digits = deduce_digits(signal)?;
+    let glyphs = Glyphs::from_str(SEVEN_SEGMENT_DIGITS)?;
 
-            Ok(interpret_output(output, &digits))
+    split_input_into_signal_and_output(input, &glyphs)?
+        .into_iter()
+        .map(|(signal, output)| match decode(&signal, &output, &glyphs) {
+            Decoding::Decoded(value) => Ok(value.parse::<u32>()?),
+            decoding => Err(anyhow!("Cannot decode output: {:?}", decoding)),
         })
         .sum()
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (seed, display) = match (extra.name.as_str(), extra.args.as_slice()) {
+        ("decode", []) => (None, "digits"),
+        ("decode", [display]) => (None, display.as_str()),
+        ("encode", [seed]) => (Some(seed.parse()?), "digits"),
+        ("encode", [seed, display]) => (Some(seed.parse()?), display.as_str()),
+        ("decode", _) => return Err(anyhow!("Usage: decode [<DISPLAY>]")),
+        _ => return Err(anyhow!("Usage: encode <SEED> [<DISPLAY>]")),
+    };
+
+    let glyphs = match display {
+        "digits" => Glyphs::from_str(SEVEN_SEGMENT_DIGITS)?,
+        "hex" => Glyphs::from_str(SEVEN_SEGMENT_HEX)?,
+        "14-segment" => Glyphs::from_str(FOURTEEN_SEGMENT_ALPHANUMERIC)?,
+        path => Glyphs::from_str(&fs::read_to_string(path)?)?,
+    };
+
+    if let Some(seed) = seed {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        return extra
+            .input
+            .lines()
+            .map(|line| Ok(encode(line, &glyphs, &mut rng)? + "\n"))
+            .collect();
+    }
+
+    let mut output = String::new();
+    for (index, (signal, display_output)) in
+        split_input_into_signal_and_output(&extra.input, &glyphs)?
+            .into_iter()
+            .enumerate()
+    {
+        output += &match decode(&signal, &display_output, &glyphs) {
+            Decoding::Decoded(value) => format!("{}: {}\n", index + 1, value),
+            Decoding::Ambiguous(values) => {
+                format!("{}: ambiguous, one of {}\n", index + 1, values.join(", "))
+            }
+            Decoding::Inconsistent => format!("{}: inconsistent\n", index + 1),
+        };
+    }
+
+    Ok(output)
+}
+
+/// Digits on a seven-segment display, with segments as labelled in the puzzle.
+const SEVEN_SEGMENT_DIGITS: &str = r"
+abcdefg
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg
+";
+
+/// Hexadecimal digits on a seven-segment display, with segments as labelled in the puzzle.
+const SEVEN_SEGMENT_HEX: &str = r"
+abcdefg
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg
+A abcdef
+b bdefg
+C abeg
+d cdefg
+E abdeg
+F abde
+";
+
+/// Digits and uppercase letters on a fourteen-segment display.
+///
+/// a to f go clockwise around the edge from the top, g and h are the left and right halves of the
+/// middle, i to k are the upper diagonals and centre from left to right, and l to n the lower
+/// ones.
+const FOURTEEN_SEGMENT_ALPHANUMERIC: &str = r"
+abcdefghijklmn
+0 abcdefkl
+1 bck
+2 abdegh
+3 abcdh
+4 bcfgh
+5 acdfgh
+6 acdefgh
+7 abc
+8 abcdefgh
+9 abcdfgh
+A abcefgh
+B abcdhjm
+C adef
+D abcdjm
+E adefg
+F aefg
+G acdefh
+H bcefgh
+I adjm
+J bcde
+K efgkn
+L def
+M bcefik
+N bcefin
+O abcdef
+P abefgh
+Q abcdefn
+R abefghn
+S adfgn
+T ajm
+U bcdef
+V efkl
+W bcefln
+X ikln
+Y ikm
+Z adkl
+";
+
+/// The wires lit up for a single symbol, as a bitmask with the first wire as bit 0.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 struct SegmentedDisplay {
-    segments: Vec<u8>,
+    wires: u32,
+}
+
+impl SegmentedDisplay {
+    fn len(&self) -> u32 {
+        self.wires.count_ones()
+    }
+}
+
+/// The symbols a display can show, each as a bitmask of the segments lit up.
+///
+/// Wires are named after the segments they are meant to be connected to.
+struct Glyphs {
+    segment_names: Vec<char>,
+    glyphs: Vec<(char, u32)>,
 }
 
-impl FromStr for SegmentedDisplay {
+impl FromStr for Glyphs {
     type Err = anyhow::Error;
 
+    /// Parses a spec: the segment names on the first line, then a symbol and its segments on each
+    /// following line.
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        let mut segments = s.as_bytes().to_vec();
-        segments.sort();
+        let mut lines = s.trim().lines();
+
+        let Some(segment_names) = lines.next() else {
+            return Err(anyhow!("Missing segment names"));
+        };
+        let segment_names = segment_names.trim().chars().collect::<Vec<_>>();
+        if segment_names.is_empty()
+            || segment_names.len() > u32::BITS as usize
+            || segment_names.iter().collect::<HashSet<_>>().len() != segment_names.len()
+        {
+            return Err(anyhow!("Invalid segment names: {:?}", segment_names));
+        }
 
-        Ok(Self { segments })
+        let mut glyphs = Glyphs {
+            segment_names,
+            glyphs: Vec::new(),
+        };
+
+        for line in lines {
+            let Some((symbol, segments)) = line.split_once(" ") else {
+                return Err(anyhow!(
+                    "Cannot split line into symbol and segments: {}",
+                    line
+                ));
+            };
+            let mut symbol = symbol.chars();
+            let (Some(symbol), None) = (symbol.next(), symbol.next()) else {
+                return Err(anyhow!("Invalid symbol: {}", line));
+            };
+            let segments = glyphs.pattern(segments.trim())?.wires;
+
+            if glyphs.glyphs.iter().any(|&(other_symbol, other_segments)| {
+                other_symbol == symbol || other_segments == segments
+            }) {
+                return Err(anyhow!("Duplicate glyph: {}", line));
+            }
+
+            glyphs.glyphs.push((symbol, segments));
+        }
+
+        Ok(glyphs)
     }
 }
 
-impl Deref for SegmentedDisplay {
-    type Target = [u8];
+impl Glyphs {
+    fn segment_count(&self) -> usize {
+        self.segment_names.len()
+    }
+
+    /// Parses the wires (or segments) named in s.
+    fn pattern(&self, s: &str) -> Result<SegmentedDisplay> {
+        let wires = s.chars().try_fold(0, |acc, wire| {
+            match self.segment_names.iter().position(|&name| name == wire) {
+                Some(index) => Ok(acc | 1 << index),
+                None => Err(anyhow!("Invalid wire: {}", wire)),
+            }
+        })?;
+
+        Ok(SegmentedDisplay { wires })
+    }
 
-    fn deref(&self) -> &Self::Target {
-        self.segments.as_slice()
+    /// Names the wires (or segments) in pattern.
+    fn name(&self, pattern: SegmentedDisplay) -> String {
+        self.segment_names
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| pattern.wires & 1 << index != 0)
+            .map(|(_, &name)| name)
+            .collect()
+    }
+
+    /// Gets the indices of the glyphs with as many segments as wires in pattern.
+    fn candidates(&self, pattern: &SegmentedDisplay) -> Vec<usize> {
+        (0..self.glyphs.len())
+            .filter(|&glyph| self.glyphs[glyph].1.count_ones() == pattern.len())
+            .collect()
     }
 }
 
-impl Sub<&SegmentedDisplay> for SegmentedDisplay {
-    type Output = Self;
+#[derive(Debug)]
+enum Decoding {
+    /// The output reads the same under every possible wiring.
+    Decoded(String),
+
+    /// The output reads differently under different possible wirings.
+    Ambiguous(Vec<String>),
 
-    fn sub(self, rhs: &Self) -> Self::Output {
-        Self {
-            segments: self
+    /// No wiring shows a glyph for every pattern.
+    Inconsistent,
+}
+
+/// Reads output by finding every way to show all the patterns in signal and output as glyphs, with
+/// some wiring of wires to segments.
+///
+/// signal may have missing or duplicate patterns.
+fn decode(signal: &[SegmentedDisplay], output: &[SegmentedDisplay], glyphs: &Glyphs) -> Decoding {
+    // Only the glyphs for output decide the reading, so they go first. The other patterns just
+    // need to fit some glyph, and the most constrained of them go first.
+    let mut patterns = output.to_vec();
+    patterns.sort_unstable_by_key(|pattern| pattern.wires);
+    patterns.dedup();
+    let output_count = patterns.len();
+
+    let mut signal = signal
+        .iter()
+        .filter(|pattern| !patterns.contains(pattern))
+        .copied()
+        .collect::<Vec<_>>();
+    signal.sort_unstable_by_key(|pattern| pattern.wires);
+    signal.dedup();
+    signal.sort_by_key(|pattern| glyphs.candidates(pattern).len());
+    patterns.extend(signal);
+
+    let mut readings = BTreeSet::new();
+    find_glyphs(
+        &patterns,
+        output_count,
+        glyphs,
+        &mut Vec::new(),
+        &mut |shown| {
+            let reading = output
                 .iter()
-                .filter(|&segment| !rhs.contains(segment))
-                .copied()
-                .collect(),
-        }
+                .filter_map(|pattern| patterns.iter().position(|other| other == pattern))
+                .map(|index| glyphs.glyphs[shown[index]].0)
+                .collect::<String>();
+
+            readings.insert(reading);
+        },
+    );
+
+    match readings.len() {
+        0 => Decoding::Inconsistent,
+        1 => Decoding::Decoded(readings.into_iter().collect()),
+        _ => Decoding::Ambiguous(readings.into_iter().collect()),
     }
 }
 
-impl Add<&SegmentedDisplay> for SegmentedDisplay {
-    type Output = Self;
+/// Extends shown (the index of the glyph for each of patterns so far) one pattern at a time,
+/// trying only glyphs with as many segments, and backtracking as soon as no wiring shows them all.
+///
+/// Calls visit with the glyphs for the first output_count patterns, once for each way to show
+/// those that lets the rest be shown too.
+fn find_glyphs(
+    patterns: &[SegmentedDisplay],
+    output_count: usize,
+    glyphs: &Glyphs,
+    shown: &mut Vec<usize>,
+    visit: &mut impl FnMut(&[usize]),
+) {
+    if shown.len() == output_count {
+        if can_show_rest(patterns, glyphs, shown) {
+            visit(shown);
+        }
+
+        return;
+    }
+
+    for glyph in glyphs.candidates(&patterns[shown.len()]) {
+        if !shown.contains(&glyph) {
+            shown.push(glyph);
 
-    fn add(self, rhs: &Self) -> Self::Output {
-        let mut segments = self.segments;
-        segments.extend(rhs.iter());
-        segments.dedup();
-        segments.sort();
+            if has_wiring(patterns, glyphs, shown) {
+                find_glyphs(patterns, output_count, glyphs, shown, visit);
+            }
 
-        Self { segments }
+            shown.pop();
+        }
     }
 }
 
-impl SegmentedDisplay {
-    fn intersect(self, rhs: &Self) -> Self {
-        Self {
-            segments: self
-                .iter()
-                .filter(|&segment| rhs.contains(segment))
-                .copied()
-                .collect(),
+/// Checks if the patterns after shown can be shown as glyphs too.
+fn can_show_rest(patterns: &[SegmentedDisplay], glyphs: &Glyphs, shown: &mut Vec<usize>) -> bool {
+    if shown.len() == patterns.len() {
+        return true;
+    }
+
+    for glyph in glyphs.candidates(&patterns[shown.len()]) {
+        if !shown.contains(&glyph) {
+            shown.push(glyph);
+            let found =
+                has_wiring(patterns, glyphs, shown) && can_show_rest(patterns, glyphs, shown);
+            shown.pop();
+
+            if found {
+                return true;
+            }
         }
     }
+
+    false
+}
+
+/// Checks if some wiring turns the first patterns into the shown glyphs.
+///
+/// A wire can only go to a segment that is lit in exactly the glyphs whose patterns have the wire,
+/// so there is such a wiring if there are as many wires as segments with each of those sets.
+fn has_wiring(patterns: &[SegmentedDisplay], glyphs: &Glyphs, shown: &[usize]) -> bool {
+    let mut wires = (0..glyphs.segment_count())
+        .map(|wire| {
+            patterns
+                .iter()
+                .map(|pattern| pattern.wires & 1 << wire != 0)
+                .take(shown.len())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut segments = (0..glyphs.segment_count())
+        .map(|segment| {
+            shown
+                .iter()
+                .map(|&glyph| glyphs.glyphs[glyph].1 & 1 << segment != 0)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    wires.sort_unstable();
+    segments.sort_unstable();
+
+    wires == segments
 }
 
 fn split_input_into_signal_and_output(
     input: &str,
+    glyphs: &Glyphs,
 ) -> Result<Vec<(Vec<SegmentedDisplay>, Vec<SegmentedDisplay>)>> {
     input
         .lines()
@@ -134,11 +451,11 @@ fn split_input_into_signal_and_output(
         .map(|(signal_part, output_part)| {
             let signal = signal_part
                 .split_ascii_whitespace()
-                .map(SegmentedDisplay::from_str)
+                .map(|pattern| glyphs.pattern(pattern))
                 .collect::<Result<Vec<_>>>()?;
             let output = output_part
                 .split_ascii_whitespace()
-                .map(SegmentedDisplay::from_str)
+                .map(|pattern| glyphs.pattern(pattern))
                 .collect::<Result<Vec<_>>>()?;
 
             Ok((signal, output))
@@ -146,132 +463,52 @@ fn split_input_into_signal_and_output(
         .collect::<Result<Vec<_>>>()
 }
 
-/// Figures out which digit each SegmentDisplay in signal represent.
-fn deduce_digits(signal: Vec<SegmentedDisplay>) -> Result<HashMap<SegmentedDisplay, u32>> {
-    // Segment count:
-    // 8 => 7
-    // 0, 6, 9 => 6
-    // 2, 3, 5 => 5
-    // 4 => 4
-    // 7 => 3
-    // 1 => 2
-
-    let mut digits = HashMap::new();
-
-    // 8, 4, 7, 1 are identified.
-    let Some(digit_8) = signal.iter().find(|digit| digit.len() == 7) else {
-        return Err(anyhow!("Cannot deduce digit 8"));
-    };
-    digits.entry(digit_8.clone()).or_insert(8);
+/// Shows text on a display with randomly crossed wires, as a line of puzzle input. The signal
+/// has every glyph once, in random order.
+fn encode(text: &str, glyphs: &Glyphs, rng: &mut impl Rng) -> Result<String> {
+    let mut wiring = (0..glyphs.segment_count()).collect::<Vec<_>>();
+    wiring.shuffle(rng);
 
-    let Some(digit_4) = signal.iter().find(|digit| digit.len() == 4) else {
-        return Err(anyhow!("Cannot deduce digit 4"));
-    };
-    digits.entry(digit_4.clone()).or_insert(4);
+    let mut signal = glyphs.glyphs.clone();
+    signal.shuffle(rng);
 
-    let Some(digit_7) = signal.iter().find(|digit| digit.len() == 3) else {
-        return Err(anyhow!("Cannot deduce digit 7"));
-    };
-    digits.entry(digit_7.clone()).or_insert(7);
+    // Wire wiring[segment] is connected to segment.
+    let mut scramble = |segments: u32| {
+        let wires = (0..wiring.len())
+            .filter(|&segment| segments & 1 << segment != 0)
+            .fold(0, |acc, segment| acc | 1 << wiring[segment]);
 
-    let Some(digit_1) = signal.iter().find(|digit| digit.len() == 2) else {
-        return Err(anyhow!("Cannot deduce digit 1"));
-    };
-    digits.entry(digit_1.clone()).or_insert(1);
-
-    let six_segments = signal
-        .iter()
-        .filter(|digit| digit.len() == 6)
-        .collect::<Vec<_>>();
-    let five_segments = signal
-        .iter()
-        .filter(|digit| digit.len() == 5)
-        .collect::<Vec<_>>();
-
-    // 2 ^ 3 ^ 5 = triple-horizontal.
-    // 7 - 1 = top-horizontal.
-    // triple-horizontal - 7 - 4 = bottom-horizontal.
-    // triple-horizontal - top-horizontal - bottom-horizontal = middle-horizontal.
-    let triple_horizontal = five_segments
-        .iter()
-        .fold(digit_8.clone(), |acc, digit| acc.intersect(digit));
-    let top_horizontal = digit_7.clone() - digit_4;
-    let bottom_horizontal = triple_horizontal.clone() - digit_7 - digit_4;
-    let middle_horizontal = triple_horizontal.clone() - &top_horizontal - &bottom_horizontal;
-
-    // middle-horizontal => 0 is identified.
-    let Some(digit_0) = six_segments
-        .clone()
-        .into_iter()
-        .find(|&digit| digit.clone().intersect(&middle_horizontal) != middle_horizontal)
-    else {
-        return Err(anyhow!("Cannot deduce digit 0"));
-    };
-    digits.entry(digit_0.clone()).or_insert(0);
+        let mut name = glyphs
+            .name(SegmentedDisplay { wires })
+            .chars()
+            .collect::<Vec<_>>();
+        name.shuffle(rng);
 
-    // 1 => 9, 6 are identified.
-    let Some(digit_9) = six_segments.clone().into_iter().find(|&digit| {
-        if digit == digit_0 {
-            return false;
-        }
-
-        digit.clone().intersect(digit_1) == *digit_1
-    }) else {
-        return Err(anyhow!("Cannot deduce digit 9"));
+        name.into_iter().collect::<String>()
     };
-    digits.entry(digit_9.clone()).or_insert(9);
 
-    let Some(digit_6) = six_segments
-        .clone()
+    let signal = signal
         .into_iter()
-        .find(|&digit| digit != digit_0 && digit != digit_9)
-    else {
-        return Err(anyhow!("Cannot deduce digit 6"));
-    };
-    digits.entry(digit_6.clone()).or_insert(6);
-
-    // 8 - 6 = top-right-vertical.
-    // 1 - top-right-vertical = bottom-right-vertical.
-    let top_right_vertical = digit_8.clone() - digit_6;
-    let bottom_right_vertical = digit_1.clone() - &top_right_vertical;
-
-    // 2, 3, 5 are identified.
-    let Some(digit_3) = five_segments.clone().into_iter().find(|&digit| {
-        digit.clone().intersect(&top_right_vertical) == top_right_vertical
-            && digit.clone().intersect(&bottom_right_vertical) == bottom_right_vertical
-    }) else {
-        return Err(anyhow!("Cannot deduce digit 3"));
-    };
-    digits.entry(digit_3.clone()).or_insert(3);
-
-    let Some(digit_2) = five_segments.clone().into_iter().find(|&digit| {
-        digit.clone().intersect(&top_right_vertical) == top_right_vertical
-            && digit.clone().intersect(&bottom_right_vertical) != bottom_right_vertical
-    }) else {
-        return Err(anyhow!("Cannot deduce digit 2"));
-    };
-    digits.entry(digit_2.clone()).or_insert(2);
-
-    let Some(digit_5) = five_segments.clone().into_iter().find(|&digit| {
-        digit.clone().intersect(&top_right_vertical) != top_right_vertical
-            && digit.clone().intersect(&bottom_right_vertical) == bottom_right_vertical
-    }) else {
-        return Err(anyhow!("Cannot deduce digit 5"));
-    };
-    digits.entry(digit_5.clone()).or_insert(5);
-
-    Ok(digits)
-}
+        .map(|(_, segments)| scramble(segments))
+        .collect::<Vec<_>>();
 
-/// Converts output to the corresponding number using the given deduced digits.
-fn interpret_output(output: Vec<SegmentedDisplay>, digits: &HashMap<SegmentedDisplay, u32>) -> u32 {
-    output
-        .into_iter()
-        .fold(0u32, |acc, value| acc * 10 + digits[&value])
+    let output = text
+        .chars()
+        .map(
+            |symbol| match glyphs.glyphs.iter().find(|&&(glyph, _)| glyph == symbol) {
+                Some(&(_, segments)) => Ok(scramble(segments)),
+                None => Err(anyhow!("Cannot display symbol: {}", symbol)),
+            },
+        )
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(format!("{} | {}", signal.join(" "), output.join(" ")))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::{Duration, Instant};
+
     use cli::trim_newlines;
 
     use super::*;
@@ -302,4 +539,75 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
 
         Ok(())
     }
+
+    #[test]
+    fn incomplete_signal() -> Result<()> {
+        let glyphs = Glyphs::from_str(SEVEN_SEGMENT_DIGITS)?;
+        let decode_line = |line| -> Result<String> {
+            let lines = split_input_into_signal_and_output(line, &glyphs)?;
+            let (signal, output) = &lines[0];
+
+            Ok(format!("{:?}", decode(signal, output, &glyphs)))
+        };
+
+        // Missing 4 and 9, with a duplicate 1.
+        assert_eq!(
+            decode_line(
+                "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb ab ab | cdfeb fcadb cdfeb cdbaf"
+            )?,
+            "Decoded(\"5353\")"
+        );
+        assert_eq!(decode_line("ab | cdfeb")?, "Ambiguous([\"2\", \"5\"])");
+        assert_eq!(decode_line("ab abc abcd | a")?, "Inconsistent");
+
+        Ok(())
+    }
+
+    #[test]
+    fn other_displays() -> Result<()> {
+        let mut rng = StdRng::seed_from_u64(8);
+
+        for (spec, text) in [
+            (SEVEN_SEGMENT_HEX, "C0FFEE"),
+            (FOURTEEN_SEGMENT_ALPHANUMERIC, "ADVENT0FC0DE2021"),
+            ("xyz\n+ xy\n- z\n= xyz", "+-="),
+        ] {
+            let glyphs = Glyphs::from_str(spec)?;
+            let line = encode(text, &glyphs, &mut rng)?;
+            let (signal, output) = &split_input_into_signal_and_output(&line, &glyphs)?[0];
+
+            assert!(matches!(
+                decode(signal, output, &glyphs),
+                Decoding::Decoded(decoded) if decoded == text
+            ));
+        }
+
+        assert!(Glyphs::from_str("ab\n1 a\n2 a").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sparse_signal() -> Result<()> {
+        let glyphs = Glyphs::from_str(FOURTEEN_SEGMENT_ALPHANUMERIC)?;
+        let start = Instant::now();
+
+        // Only a few of the fourteen wires are lit, which leaves many wirings open.
+        for (line, expected) in [
+            ("abc | abcd", Some(vec!["C", "I"])),
+            ("abcdefgh | gkce khc cfgd", None),
+        ] {
+            let (signal, output) = &split_input_into_signal_and_output(line, &glyphs)?[0];
+
+            match (decode(signal, output, &glyphs), expected) {
+                (Decoding::Ambiguous(readings), Some(expected)) => assert_eq!(readings, expected),
+                (Decoding::Inconsistent, None) => (),
+                _ => panic!("Unexpected decoding: {}", line),
+            }
+        }
+
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        Ok(())
+    }
 }
@@ -1,11 +1,15 @@
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 
-use cli::{Part, get_part};
+use cli::{Command, Extra, Part, get_command};
 
 fn main() {
-    match get_part("inputs/day-9.txt") {
-        Ok(Part::Part1(input)) => println!("{:?}", part_1(&input)),
-        Ok(Part::Part2(input)) => println!("{:?}", part_2(&input)),
+    match get_command("inputs/day-9.txt", &["basins", "map"]) {
+        Ok(Command::Part(Part::Part1(input))) => println!("{:?}", part_1(&input)),
+        Ok(Command::Part(Part::Part2(input))) => println!("{:?}", part_2(&input)),
+        Ok(Command::Extra(extra)) => match run_extra(&extra) {
+            Ok(output) => print!("{output}"),
+            Err(error) => println!("{error:?}"),
+        },
         Err(error) => println!("{error:?}"),
     }
 }
@@ -29,26 +33,79 @@ fn part_1(input: &str) -> Result<u32> {
 }
 
 fn part_2(input: &str) -> Result<usize> {
-    let (mut heightmap, grid_size) = parse_input_into_heightmap(input)?;
+    let (heightmap, grid_size) = parse_input_into_heightmap(input)?;
 
-    let mut basin_sizes = (0..grid_size.0)
-        .flat_map(|row| {
-            (0..grid_size.1)
-                .filter_map(|col| {
-                    if is_low_point((row, col), &heightmap, &grid_size) {
-                        Some(flood_basin((row, col), &mut heightmap, &grid_size))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
+    let mut basin_sizes = label_basins(&heightmap, &grid_size, DrainageRules::default())
+        .iter()
+        .map(Basin::size)
         .collect::<Vec<_>>();
     basin_sizes.sort_by(|a, b| b.cmp(a));
 
+    if basin_sizes.len() < 3 {
+        return Err(anyhow!("Fewer than 3 basins"));
+    }
+
     Ok(basin_sizes[0] * basin_sizes[1] * basin_sizes[2])
 }
 
+fn run_extra(extra: &Extra) -> Result<String> {
+    let (format, options) = match (extra.name.as_str(), extra.args.split_first()) {
+        ("map", Some((format, options))) if format == "terminal" || format == "ppm" => {
+            (Some(format.as_str()), options)
+        }
+        ("map", _) => {
+            return Err(anyhow!(
+                "Usage: map <terminal|ppm> [divide=<HEIGHT>] [diagonals] [descent]"
+            ));
+        }
+        _ => (None, extra.args.as_slice()),
+    };
+
+    let mut rules = DrainageRules::default();
+    for option in options {
+        match option.split_once("=") {
+            Some(("divide", height)) => {
+                rules.divide_height = height.parse()?;
+
+                // Heights run from 0 to 9, so 10 already means no divides at all.
+                if rules.divide_height > 10 {
+                    return Err(anyhow!("Invalid divide height: {}", height));
+                }
+            }
+            None if option == "diagonals" => rules.diagonals = true,
+            None if option == "descent" => rules.steepest_descent = true,
+            _ => return Err(anyhow!("Invalid option: {}", option)),
+        }
+    }
+
+    let (heightmap, grid_size) = parse_input_into_heightmap(&extra.input)?;
+    let basins = label_basins(&heightmap, &grid_size, rules);
+
+    match format {
+        Some("terminal") => return Ok(basin_map_to_terminal(&heightmap, &grid_size, &basins)),
+        Some(_) => return Ok(basin_map_to_ppm(&grid_size, &basins)),
+        None => (),
+    }
+
+    let mut output = String::new();
+    for basin in &basins {
+        output += &format!(
+            "basin at {:?}: size {}, height {} to {} (mean {:.2}), cells {:?}\n",
+            basin.low_point,
+            basin.size(),
+            basin.min_height,
+            basin.max_height,
+            basin.mean_height,
+            basin.cells
+        );
+    }
+    for (cell, low_points) in multiple_drainage(&heightmap, &grid_size, rules, &basins) {
+        output += &format!("{cell:?} drains to {low_points:?}\n");
+    }
+
+    Ok(output)
+}
+
 type Coord = (usize, usize);
 
 type GridSize = (usize, usize);
@@ -89,37 +146,333 @@ fn is_low_point(pos: Coord, heightmap: &[Vec<u8>], grid_size: &GridSize) -> bool
         .all(|neighbour| *neighbour > heightmap[pos.0][pos.1])
 }
 
-fn flood_basin(low_point: Coord, heightmap: &mut [Vec<u8>], grid_size: &GridSize) -> usize {
-    // Termination case.
-    if matches!(heightmap[low_point.0][low_point.1], b'9' | u8::MAX) {
-        return 0;
+/// How cells are grouped into [Basin]s.
+#[derive(Clone, Copy)]
+struct DrainageRules {
+    /// Cells at or above this height belong to no basin.
+    divide_height: u8,
+
+    /// Whether diagonally adjacent cells are neighbours too.
+    diagonals: bool,
+
+    /// Whether each cell only joins its lowest neighbour, if lower, so that a basin is all the
+    /// cells draining to the same low point. Otherwise, a basin is all the cells connected without
+    /// crossing a divide.
+    steepest_descent: bool,
+}
+
+impl Default for DrainageRules {
+    fn default() -> Self {
+        DrainageRules {
+            divide_height: 9,
+            diagonals: false,
+            steepest_descent: false,
+        }
     }
+}
 
-    // Mark the position as done.
-    heightmap[low_point.0][low_point.1] = u8::MAX;
+/// A group of cells in a heightmap, according to some [DrainageRules].
+struct Basin {
+    /// The lowest cell, taking the first in reading order if tied.
+    low_point: Coord,
 
+    /// All cells in reading order.
+    cells: Vec<Coord>,
+
+    min_height: u8,
+    max_height: u8,
+    mean_height: f64,
+}
+
+impl Basin {
+    fn size(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// Gets the up to 4 cells next to pos, or up to 8 including diagonals.
+fn neighbours(pos: Coord, grid_size: &GridSize, diagonals: bool) -> Vec<Coord> {
     let mut neighbours = Vec::new();
 
-    if low_point.0 > 0 {
-        neighbours.push((low_point.0 - 1, low_point.1));
+    if diagonals {
+        let rows = pos.0.saturating_sub(1)..=(pos.0 + 1).min(grid_size.0 - 1);
+
+        for row in rows {
+            for col in pos.1.saturating_sub(1)..=(pos.1 + 1).min(grid_size.1 - 1) {
+                if row != pos.0 && col != pos.1 {
+                    neighbours.push((row, col));
+                }
+            }
+        }
+    }
+
+    if pos.0 > 0 {
+        neighbours.push((pos.0 - 1, pos.1));
+    }
+
+    if pos.1 < grid_size.1 - 1 {
+        neighbours.push((pos.0, pos.1 + 1));
+    }
+
+    if pos.0 < grid_size.0 - 1 {
+        neighbours.push((pos.0 + 1, pos.1));
     }
 
-    if low_point.1 < grid_size.1 - 1 {
-        neighbours.push((low_point.0, low_point.1 + 1));
+    if pos.1 > 0 {
+        neighbours.push((pos.0, pos.1 - 1));
     }
 
-    if low_point.0 < grid_size.0 - 1 {
-        neighbours.push((low_point.0 + 1, low_point.1));
+    neighbours
+}
+
+/// Finds all the [Basin]s in heightmap, ordered by their first cell in reading order.
+///
+/// Cells are joined with a union-find instead of flooding, so large heightmaps are fine.
+fn label_basins(heightmap: &[Vec<u8>], grid_size: &GridSize, rules: DrainageRules) -> Vec<Basin> {
+    let index = |pos: Coord| pos.0 * grid_size.1 + pos.1;
+    let is_divide = |(row, col): Coord| heightmap[row][col] >= b'0' + rules.divide_height;
+    let mut parents = UnionFind::new(grid_size.0 * grid_size.1);
+
+    for row in 0..grid_size.0 {
+        for col in 0..grid_size.1 {
+            if is_divide((row, col)) {
+                continue;
+            }
+
+            let neighbours = neighbours((row, col), grid_size, rules.diagonals);
+
+            if rules.steepest_descent {
+                // Join the lowest neighbour, if any is lower. Divides are never lower.
+                if let Some(lowest) = neighbours
+                    .into_iter()
+                    .min_by_key(|&(n_row, n_col)| heightmap[n_row][n_col])
+                    && heightmap[lowest.0][lowest.1] < heightmap[row][col]
+                {
+                    parents.union(index((row, col)), index(lowest));
+                }
+            } else {
+                for neighbour in neighbours {
+                    if !is_divide(neighbour) {
+                        parents.union(index((row, col)), index(neighbour));
+                    }
+                }
+            }
+        }
     }
 
-    if low_point.1 > 0 {
-        neighbours.push((low_point.0, low_point.1 - 1));
+    // Group the cells by their root.
+    let mut basin_indices = vec![None; grid_size.0 * grid_size.1];
+    let mut basins: Vec<Vec<Coord>> = Vec::new();
+
+    for (row, heights) in heightmap.iter().enumerate() {
+        for col in 0..heights.len() {
+            if is_divide((row, col)) {
+                continue;
+            }
+
+            let root = parents.find(index((row, col)));
+            let basin = *basin_indices[root].get_or_insert_with(|| {
+                basins.push(Vec::new());
+                basins.len() - 1
+            });
+
+            basins[basin].push((row, col));
+        }
     }
 
-    1 + neighbours
+    basins
         .into_iter()
-        .map(|neighbour| flood_basin(neighbour, heightmap, grid_size))
-        .sum::<usize>()
+        .filter_map(|cells| {
+            let height = |&(row, col): &Coord| heightmap[row][col] - b'0';
+            let low_point = *cells.iter().min_by_key(|cell| height(cell))?;
+            let max_height = cells.iter().map(height).max()?;
+            let total_height = cells
+                .iter()
+                .map(|cell| f64::from(height(cell)))
+                .sum::<f64>();
+
+            Some(Basin {
+                low_point,
+                min_height: height(&low_point),
+                max_height,
+                mean_height: total_height / cells.len() as f64,
+                cells,
+            })
+        })
+        .collect()
+}
+
+/// Finds the cells from which water, always flowing to a strictly lower neighbour, can reach more
+/// than one of basins. Each is returned with the low points of the basins it can reach.
+fn multiple_drainage(
+    heightmap: &[Vec<u8>],
+    grid_size: &GridSize,
+    rules: DrainageRules,
+    basins: &[Basin],
+) -> Vec<(Coord, Vec<Coord>)> {
+    let mut basin_indices = vec![vec![None; grid_size.1]; grid_size.0];
+    for (index, basin) in basins.iter().enumerate() {
+        for &(row, col) in &basin.cells {
+            basin_indices[row][col] = Some(index);
+        }
+    }
+
+    let mut cells = (0..grid_size.0)
+        .flat_map(|row| (0..grid_size.1).map(move |col| (row, col)))
+        .filter(|&(row, col)| heightmap[row][col] < b'0' + rules.divide_height)
+        .collect::<Vec<_>>();
+    cells.sort_by_key(|&(row, col)| heightmap[row][col]);
+
+    // Visiting from the lowest cell up, every lower neighbour is already resolved.
+    let mut low_points: Vec<Vec<Vec<Coord>>> = vec![vec![Vec::new(); grid_size.1]; grid_size.0];
+    let mut multiple = Vec::new();
+
+    for (row, col) in cells {
+        let mut reachable = neighbours((row, col), grid_size, rules.diagonals)
+            .into_iter()
+            .filter(|&(n_row, n_col)| heightmap[n_row][n_col] < heightmap[row][col])
+            .flat_map(|(n_row, n_col)| low_points[n_row][n_col].clone())
+            .collect::<Vec<_>>();
+        reachable.sort_unstable();
+        reachable.dedup();
+
+        if reachable.is_empty() {
+            reachable.push((row, col));
+        }
+
+        let mut reachable_basins = reachable
+            .iter()
+            .filter_map(|&(row, col)| basin_indices[row][col])
+            .collect::<Vec<_>>();
+        reachable_basins.sort_unstable();
+        reachable_basins.dedup();
+
+        if reachable_basins.len() > 1 {
+            let basin_low_points = reachable_basins
+                .into_iter()
+                .map(|basin| basins[basin].low_point)
+                .collect();
+
+            multiple.push(((row, col), basin_low_points));
+        }
+
+        low_points[row][col] = reachable;
+    }
+
+    multiple.sort_unstable();
+    multiple
+}
+
+/// Picks a distinct colour for each basin index.
+fn basin_colour(basin: usize) -> (u8, u8, u8) {
+    // Step around the colour wheel by the golden ratio, so neighbouring indices differ a lot.
+    let hue = (basin as f64 * 0.618_034).fract() * 6.0;
+    let fall = (hue.fract() * 200.0) as u8;
+
+    match hue as u8 {
+        0 => (230, 30 + fall, 30),
+        1 => (230 - fall, 230, 30),
+        2 => (30, 230, 30 + fall),
+        3 => (30, 230 - fall, 230),
+        4 => (30 + fall, 30, 230),
+        _ => (230, 30, 230 - fall),
+    }
+}
+
+/// Maps each cell to the index of its basin, if any.
+fn basin_grid(grid_size: &GridSize, basins: &[Basin]) -> Vec<Vec<Option<usize>>> {
+    let mut grid = vec![vec![None; grid_size.1]; grid_size.0];
+
+    for (index, basin) in basins.iter().enumerate() {
+        for &(row, col) in &basin.cells {
+            grid[row][col] = Some(index);
+        }
+    }
+
+    grid
+}
+
+/// Renders the heightmap with each basin in its own background colour, for a terminal that
+/// supports 24-bit colour. Divides are left uncoloured.
+fn basin_map_to_terminal(heightmap: &[Vec<u8>], grid_size: &GridSize, basins: &[Basin]) -> String {
+    let mut output = String::new();
+
+    for (heights, basin_row) in heightmap.iter().zip(basin_grid(grid_size, basins)) {
+        for (&height, basin) in heights.iter().zip(basin_row) {
+            match basin.map(basin_colour) {
+                Some((red, green, blue)) => {
+                    output += &format!("\x1b[30;48;2;{red};{green};{blue}m{}", height as char)
+                }
+                None => output += &format!("\x1b[0m{}", height as char),
+            }
+        }
+
+        output += "\x1b[0m\n";
+    }
+
+    output
+}
+
+/// Renders each basin in its own colour as a plain (ASCII) PPM image, one pixel per cell.
+/// Divides are black.
+fn basin_map_to_ppm(grid_size: &GridSize, basins: &[Basin]) -> String {
+    let mut ppm = format!("P3\n{} {}\n255\n", grid_size.1, grid_size.0);
+
+    for basin_row in basin_grid(grid_size, basins) {
+        let row = basin_row
+            .into_iter()
+            .map(|basin| {
+                let (red, green, blue) = basin.map_or((0, 0, 0), basin_colour);
+
+                format!("{red} {green} {blue}")
+            })
+            .collect::<Vec<_>>();
+
+        ppm += &row.join(" ");
+        ppm += "\n";
+    }
+
+    ppm
+}
+
+/// Disjoint sets of indices, each identified by a root index.
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        UnionFind {
+            parents: (0..size).collect(),
+            sizes: vec![1; size],
+        }
+    }
+
+    fn find(&mut self, mut index: usize) -> usize {
+        while self.parents[index] != index {
+            // Path halving.
+            self.parents[index] = self.parents[self.parents[index]];
+            index = self.parents[index];
+        }
+
+        index
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+
+        // Attach the smaller set under the larger one.
+        if self.sizes[a] < self.sizes[b] {
+            (a, b) = (b, a);
+        }
+
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
 }
 
 #[cfg(test)]
@@ -149,4 +502,97 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn basin_report() -> Result<()> {
+        let (heightmap, grid_size) = parse_input_into_heightmap(trim_newlines(EXAMPLE_INPUT))?;
+        let basins = label_basins(&heightmap, &grid_size, DrainageRules::default());
+
+        assert_eq!(
+            basins
+                .iter()
+                .map(|basin| (basin.low_point, basin.size(), basin.max_height))
+                .collect::<Vec<_>>(),
+            vec![
+                ((0, 1), 3, 3),
+                ((0, 9), 9, 4),
+                ((2, 2), 14, 8),
+                ((4, 6), 9, 8)
+            ]
+        );
+        assert_eq!(basins[0].cells, vec![(0, 0), (0, 1), (1, 0)]);
+
+        // The 2 drains to both 0s, which are in the same basin, unless it only joins the right 0.
+        let (heightmap, grid_size) = parse_input_into_heightmap("020\n999")?;
+        for (rules, expected) in [
+            (DrainageRules::default(), vec![]),
+            (
+                DrainageRules {
+                    steepest_descent: true,
+                    ..DrainageRules::default()
+                },
+                vec![((0, 1), vec![(0, 0), (0, 2)])],
+            ),
+        ] {
+            let basins = label_basins(&heightmap, &grid_size, rules);
+            assert_eq!(
+                multiple_drainage(&heightmap, &grid_size, rules, &basins),
+                expected
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn drainage_rules() -> Result<()> {
+        let basin_count = |input, rules| -> Result<usize> {
+            let (heightmap, grid_size) = parse_input_into_heightmap(input)?;
+
+            Ok(label_basins(&heightmap, &grid_size, rules).len())
+        };
+        let rules = DrainageRules::default();
+
+        assert_eq!(basin_count("19\n91", rules)?, 2);
+        assert_eq!(
+            basin_count(
+                "19\n91",
+                DrainageRules {
+                    diagonals: true,
+                    ..rules
+                }
+            )?,
+            1
+        );
+        assert_eq!(
+            basin_count(
+                "1251",
+                DrainageRules {
+                    divide_height: 5,
+                    ..rules
+                }
+            )?,
+            2
+        );
+        assert_eq!(basin_count("0120", rules)?, 1);
+        assert_eq!(
+            basin_count(
+                "0120",
+                DrainageRules {
+                    steepest_descent: true,
+                    ..rules
+                }
+            )?,
+            2
+        );
+
+        let (heightmap, grid_size) = parse_input_into_heightmap("09\n90")?;
+        let basins = label_basins(&heightmap, &grid_size, rules);
+        assert_eq!(
+            basin_map_to_ppm(&grid_size, &basins),
+            "P3\n2 2\n255\n230 30 30 0 0 0\n0 0 0 30 89 230\n"
+        );
+
+        Ok(())
+    }
 }